@@ -0,0 +1,105 @@
+use rdev::Key;
+use serde::Deserialize;
+
+use crate::{icon::Icon, keys::{self, Modifier}};
+
+// What a button does, written in the config as e.g. action = "undo",
+// action = { keys = "ctrl+z" } or action = { text = "fn main() {}" }
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+  // Commands for the writer itself
+  Clear,
+  Submit,
+  Undo,
+  Redo,
+  Eraser,
+  Backend,
+  // Keys typed into the focused window
+  Enter,
+  Space,
+  DelWord,
+  Keys(KeyChord),
+  Text(String)
+}
+
+impl Action {
+  pub fn icon(&self) -> Option<Icon> {
+    match self {
+      Action::Clear => Some(Icon::Clear),
+      Action::Submit => Some(Icon::Submit),
+      Action::Undo => Some(Icon::Undo),
+      Action::Redo => Some(Icon::Redo),
+      Action::Eraser => Some(Icon::Eraser),
+      Action::Backend => Some(Icon::Backend),
+      Action::Enter => Some(Icon::Enter),
+      Action::Space => Some(Icon::Space),
+      Action::DelWord => Some(Icon::DelWord),
+      Action::Keys(_) | Action::Text(_) => None
+    }
+  }
+}
+
+// Keys pressed together, written like "ctrl+shift+tab"
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(try_from = "String")]
+pub struct KeyChord(pub Vec<Key>);
+
+impl TryFrom<String> for KeyChord {
+  type Error = String;
+  
+  fn try_from(value: String) -> Result<Self, Self::Error> {
+    let keys = value.split('+')
+      .map(|name| {
+        Modifier::parse(name).map(Modifier::key)
+          .or_else(|| keys::parse_key(name).map(|(key, _)| key))
+          .ok_or_else(|| format!("unknown key '{}' in '{value}'", name.trim()))
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    
+    Ok(Self(keys))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  fn chord(value: &str) -> Result<KeyChord, String> {
+    KeyChord::try_from(value.to_string())
+  }
+  
+  #[test]
+  fn chord_keeps_key_order() {
+    assert_eq!(chord("ctrl+shift+tab"), Ok(KeyChord(vec![Key::ControlLeft, Key::ShiftLeft, Key::Tab])));
+    assert_eq!(chord("F5"), Ok(KeyChord(vec![Key::F5])));
+  }
+  
+  #[test]
+  fn chord_names_are_loose() {
+    assert_eq!(chord(" Control + Z "), Ok(KeyChord(vec![Key::ControlLeft, Key::KeyZ])));
+    assert_eq!(chord("alt+enter"), chord("alt+return"));
+    assert_eq!(chord("super+del"), Ok(KeyChord(vec![Key::MetaLeft, Key::Delete])));
+  }
+  
+  #[test]
+  fn chord_rejects_unknown_keys() {
+    assert_eq!(chord("ctrl+hyper"), Err("unknown key 'hyper' in 'ctrl+hyper'".to_string()));
+    assert!(chord("").is_err());
+    assert!(chord("ctrl+").is_err());
+  }
+  
+  #[test]
+  fn actions_read_from_config() {
+    #[derive(Deserialize)]
+    struct Button {
+      action: Action
+    }
+    
+    let action = |content: &str| toml::from_str::<Button>(content).map(|button| button.action);
+    assert_eq!(action("action = \"delword\""), Ok(Action::DelWord));
+    assert_eq!(action("action = { keys = \"ctrl+z\" }"), Ok(Action::Keys(KeyChord(vec![Key::ControlLeft, Key::KeyZ]))));
+    assert_eq!(action("action = { text = \"x\" }"), Ok(Action::Text("x".to_string())));
+    assert!(action("action = { keys = \"ctrl+nope\" }").is_err());
+  }
+}
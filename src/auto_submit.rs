@@ -0,0 +1,46 @@
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+
+// Submits the text by itself once the pen has been away for a while
+pub struct AutoSubmit {
+  // None if auto submit is off
+  delay: Option<Duration>,
+  // When the last stroke was finished, None if not counting down
+  finished_at: Option<Instant>
+}
+
+impl AutoSubmit {
+  pub fn new(config: &Config) -> Self {
+    Self {
+      delay: config.auto_submit_ms.map(Duration::from_millis),
+      finished_at: None
+    }
+  }
+  
+  pub fn pen_down(&mut self) {
+    self.finished_at = None;
+  }
+  
+  // Only changes to the ink start the countdown, not e.g. pressing a button
+  pub fn stroke_finished(&mut self) {
+    if self.delay.is_some() {
+      self.finished_at = Some(Instant::now());
+    }
+  }
+  
+  pub fn cancel(&mut self) {
+    self.finished_at = None;
+  }
+  
+  // Part of the delay left, from 1.0 down to 0.0, None if not counting down
+  pub fn remaining(&self) -> Option<f32> {
+    let delay = self.delay?;
+    let elapsed = self.finished_at?.elapsed();
+    Some(1.0 - (elapsed.as_secs_f32() / delay.as_secs_f32()).min(1.0))
+  }
+  
+  pub fn is_due(&self) -> bool {
+    self.remaining() == Some(0.0)
+  }
+}
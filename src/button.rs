@@ -1,58 +1,195 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::HashSet, rc::Rc};
 
 use sdl3::{pixels::Color, render::Canvas, video::Window};
 
-use crate::shapes::{Rect, Point};
+use crate::{font, icon::Icon, input::{InputSource, PointerEvent, PointerId}, shapes::{Rect, Point}, widget::Widget};
+
+const LABEL_SCALE: f32 = 1.0;
+// Space between the edge, icon and label
+const SPACING: f32 = 4.0;
 
 pub struct Button {
   canvas: Rc<RefCell<Canvas<Window>>>,
   bound: Rect,
-  is_down: bool,
-  is_pressed: bool
+  // Pointer holding the button down and whether it is still over it,
+  // the button is pressed only if it is let go over the button
+  down: Option<(PointerId, bool)>,
+  // Pointers over the button, each one as of its last motion
+  hovered_by: HashSet<PointerId>,
+  is_pressed: bool,
+  // For buttons acting as switch, drawn darker when on
+  is_toggled: bool,
+  // Hidden buttons are not drawn and cannot be pressed
+  is_visible: bool,
+  label: String,
+  icon: Option<Icon>
 }
 
 impl Button {
-  pub fn new(bound: Rect, canvas: Rc<RefCell<Canvas<Window>>>) -> Self {
+  pub fn new(canvas: Rc<RefCell<Canvas<Window>>>) -> Self {
     Self {
       is_pressed: false,
-      is_down: false,
-      bound,
+      down: None,
+      hovered_by: HashSet::new(),
+      is_toggled: false,
+      is_visible: true,
+      label: String::new(),
+      icon: None,
+      bound: Rect::default(),
       canvas
     }
   }
   
-  pub fn reset(&mut self) {
-    self.is_pressed = false;
+  pub fn with_label(mut self, label: &str) -> Self {
+    self.set_label(label);
+    self
   }
   
-  pub fn draw(&self) {
-    let mut canvas = self.canvas.borrow_mut();
-    canvas.set_draw_color(Color::RGB(0xBB, 0xBB, 0xBB));
-    let _ = canvas.fill_rect(Some(self.bound.clone().into()))
-      .map_err(|e| log::warn!("error calling canvas.fill_rect {e}"));
+  pub fn with_icon(mut self, icon: Icon) -> Self {
+    self.icon = Some(icon);
+    self
+  }
+  
+  pub fn set_label(&mut self, label: &str) {
+    if self.label != label {
+      self.label = label.to_string();
+    }
   }
   
   pub fn is_pressed(&self) -> bool {
     self.is_pressed
   }
   
-  pub fn set_bound(&mut self, bound: Rect) {
-    self.bound = bound;
+  pub fn set_toggled(&mut self, toggled: bool) {
+    self.is_toggled = toggled;
+  }
+  
+  pub fn set_visible(&mut self, visible: bool) {
+    self.is_visible = visible;
+    if !visible {
+      self.down = None;
+      self.hovered_by.clear();
+    }
   }
   
-  pub fn pen_up(&mut self, _x: f32, _y: f32) {
-    if self.is_down {
+  fn pen_up(&mut self, x: f32, y: f32, pointer: PointerId) {
+    // Fingers do not hover once lifted
+    if pointer.source == InputSource::Touch {
+      self.hovered_by.remove(&pointer);
+    }
+    
+    let Some((down_pointer, _)) = self.down else {
+      return;
+    };
+    
+    if down_pointer != pointer {
+      return;
+    }
+    
+    // Letting go outside cancels
+    self.down = None;
+    if self.bound.contains(&Point { x, y }) {
       self.is_pressed = true;
-      self.is_down = false;
     }
   }
   
-  pub fn pen_down(&mut self, x: f32, y: f32) {
-    if !self.bound.contains(&Point { x, y }) {
+  fn pen_down(&mut self, x: f32, y: f32, pointer: PointerId) {
+    if self.down.is_some() || !self.bound.contains(&Point { x, y }) {
       return;
     }
     
-    self.is_down = true;
+    self.down = Some((pointer, true));
+  }
+  
+  fn pen_motion(&mut self, x: f32, y: f32, pointer: PointerId) {
+    let is_inside = self.bound.contains(&Point { x, y });
+    if is_inside {
+      self.hovered_by.insert(pointer);
+    } else {
+      self.hovered_by.remove(&pointer);
+    }
+    
+    if let Some((down_pointer, is_over)) = self.down.as_mut() && *down_pointer == pointer {
+      *is_over = is_inside;
+    }
   }
 }
 
+impl Widget for Button {
+  fn set_bound(&mut self, bound: Rect) {
+    self.bound = bound;
+  }
+  
+  fn draw(&self) {
+    if !self.is_visible {
+      return;
+    }
+    
+    let mut canvas = self.canvas.borrow_mut();
+    let is_held = matches!(self.down, Some((_, true)));
+    let foreground = if self.is_toggled {
+      canvas.set_draw_color(if is_held { Color::RGB(0x55, 0x55, 0x55) } else { Color::RGB(0x77, 0x77, 0x77) });
+      Color::WHITE
+    } else {
+      if is_held {
+        canvas.set_draw_color(Color::RGB(0x99, 0x99, 0x99));
+      } else if !self.hovered_by.is_empty() {
+        canvas.set_draw_color(Color::RGB(0xCC, 0xCC, 0xCC));
+      } else {
+        canvas.set_draw_color(Color::RGB(0xBB, 0xBB, 0xBB));
+      }
+      Color::BLACK
+    };
+    let _ = canvas.fill_rect(Some(self.bound.clone().into()))
+      .map_err(|e| log::warn!("error calling canvas.fill_rect {e}"));
+    
+    // Icon takes what the label leaves
+    let label_height = if self.label.is_empty() { 0.0 } else { font::line_height(LABEL_SCALE) };
+    let mut label_y = (self.bound.y1 + self.bound.y2 - label_height) / 2.0;
+    if let Some(icon) = &self.icon {
+      let icon_bound = Rect {
+        x1: self.bound.x1 + SPACING,
+        y1: self.bound.y1 + SPACING,
+        x2: self.bound.x2 - SPACING,
+        y2: self.bound.y2 - SPACING - if label_height > 0.0 { label_height + SPACING } else { 0.0 }
+      };
+      if icon_bound.y2 > icon_bound.y1 {
+        icon.draw(&mut canvas, &icon_bound, foreground);
+        label_y = icon_bound.y2 + SPACING;
+      }
+    }
+    
+    if !self.label.is_empty() {
+      // Long labels are cut off at the edges
+      let label_x = (self.bound.x1 + self.bound.x2 - font::text_width(&self.label, LABEL_SCALE)) / 2.0;
+      let clip: sdl3::rect::Rect = self.bound.clone().into();
+      canvas.set_clip_rect(clip);
+      font::draw_text(&mut canvas, &self.label, label_x.max(self.bound.x1 + SPACING), label_y, LABEL_SCALE, foreground);
+      canvas.set_clip_rect(None::<sdl3::rect::Rect>);
+    }
+  }
+  
+  fn begin_frame(&mut self) {
+    self.is_pressed = false;
+  }
+  
+  fn pointer_event(&mut self, event: &PointerEvent) {
+    if !self.is_visible {
+      return;
+    }
+    
+    match *event {
+      PointerEvent::Down { pointer, x, y, .. } => self.pen_down(x, y, pointer),
+      PointerEvent::Up { pointer, x, y, .. } => self.pen_up(x, y, pointer),
+      PointerEvent::Motion { pointer, x, y, .. } => self.pen_motion(x, y, pointer)
+    }
+  }
+  
+  // Releases the button without pressing it
+  fn cancel(&mut self, pointer: PointerId) {
+    self.hovered_by.remove(&pointer);
+    if self.down.is_some_and(|(down_pointer, _)| down_pointer == pointer) {
+      self.down = None;
+    }
+  }
+}
@@ -0,0 +1,241 @@
+use std::path::{Path, PathBuf};
+
+use crate::{config::Config, data_dir, processor::Recognition};
+
+// Suggested words are at most this many edits away from the recognized word
+const MAX_EDIT_DISTANCE: usize = 2;
+
+// Used if no dictionary is found in the data dirs
+const SYSTEM_WORDS: &str = "/usr/share/dict/words";
+
+// Words the recognized text can be corrected to
+pub struct Dictionary {
+  // Lowercase, in the order of the file
+  words: Vec<String>
+}
+
+impl Dictionary {
+  // Reads words.txt, one word per line, from a dictionary directory.
+  // STYLUS_WRITING_DICTIONARY or dictionary in the config can point to one.
+  // Empty if no dictionary is found
+  pub fn load(config: &Config) -> Self {
+    let path = data_dir::find("dictionary", &["words.txt"], "STYLUS_WRITING_DICTIONARY", config.dictionary.as_deref())
+      .map(|dir| dir.join("words.txt"))
+      .or_else(|tried| {
+        let system = PathBuf::from(SYSTEM_WORDS);
+        if system.is_file() {
+          Ok(system)
+        } else {
+          Err(tried)
+        }
+      });
+    
+    match path {
+      Ok(path) => Self::read(&path),
+      Err(tried) => {
+        let tried: Vec<String> = tried.iter().map(|dir| dir.display().to_string()).collect();
+        log::warn!("No dictionary found, looked for words.txt in: {}, and {SYSTEM_WORDS}", tried.join(", "));
+        Self { words: Vec::new() }
+      }
+    }
+  }
+  
+  fn read(path: &Path) -> Self {
+    let content = match std::fs::read_to_string(path) {
+      Ok(content) => content,
+      Err(e) => {
+        log::warn!("Error reading dictionary {}: {e}", path.display());
+        return Self { words: Vec::new() };
+      }
+    };
+    
+    let mut words: Vec<String> = content.lines()
+      .map(str::trim)
+      .filter(|word| !word.is_empty() && word.chars().all(char::is_alphabetic))
+      .map(str::to_lowercase)
+      .collect();
+    // Keeps the first of each, the file may list a word in several cases
+    let mut seen = std::collections::HashSet::new();
+    words.retain(|word| seen.insert(word.clone()));
+    
+    log::info!("Loaded {} words from {}", words.len(), path.display());
+    Self { words }
+  }
+  
+  // Closest words first, in the case of the given word
+  pub fn suggest(&self, word: &str, count: usize) -> Vec<String> {
+    let lower = word.to_lowercase();
+    if lower.is_empty() || !lower.chars().all(char::is_alphabetic) {
+      return Vec::new();
+    }
+    
+    let lower: Vec<char> = lower.chars().collect();
+    let mut found: Vec<(usize, &String)> = self.words.iter()
+      .filter_map(|candidate| {
+        let candidate_chars: Vec<char> = candidate.chars().collect();
+        edit_distance(&lower, &candidate_chars)
+          .filter(|distance| *distance > 0)
+          .map(|distance| (distance, candidate))
+      })
+      .collect();
+    // Stable, so equally close words keep the dictionary order
+    found.sort_by_key(|(distance, _)| *distance);
+    
+    found.into_iter()
+      .take(count)
+      .map(|(_, candidate)| match_case(word, candidate))
+      .collect()
+  }
+}
+
+// Levenshtein distance, None if it is over MAX_EDIT_DISTANCE
+fn edit_distance(a: &[char], b: &[char]) -> Option<usize> {
+  if a.len().abs_diff(b.len()) > MAX_EDIT_DISTANCE {
+    return None;
+  }
+  
+  let mut previous: Vec<usize> = (0..=b.len()).collect();
+  let mut current = vec![0; b.len() + 1];
+  for i in 1..=a.len() {
+    current[0] = i;
+    for j in 1..=b.len() {
+      let substitution = previous[j - 1] + usize::from(a[i - 1] != b[j - 1]);
+      current[j] = substitution.min(previous[j] + 1).min(current[j - 1] + 1);
+    }
+    
+    // Distance never goes down from one row to the next
+    if current.iter().all(|distance| *distance > MAX_EDIT_DISTANCE) {
+      return None;
+    }
+    std::mem::swap(&mut previous, &mut current);
+  }
+  
+  Some(previous[b.len()]).filter(|distance| *distance <= MAX_EDIT_DISTANCE)
+}
+
+// Candidate with the capitalization of the original word
+fn match_case(original: &str, candidate: &str) -> String {
+  let mut original_chars = original.chars();
+  let first_upper = original_chars.next().is_some_and(char::is_uppercase);
+  let all_upper = first_upper && original.chars().count() > 1 && original_chars.all(char::is_uppercase);
+  
+  if all_upper {
+    return candidate.to_uppercase();
+  }
+  
+  let mut chars = candidate.chars();
+  match chars.next() {
+    Some(first) if first_upper => first.to_uppercase().chain(chars).collect(),
+    _ => candidate.to_string()
+  }
+}
+
+// Word most likely to be wrong and what it could be instead
+#[derive(Clone)]
+pub struct Candidates {
+  // Index into the regions of the recognition
+  pub region: usize,
+  // Best first
+  pub texts: Vec<String>
+}
+
+// Candidates for the least confident word which has any, the
+// backend's alternatives come before dictionary suggestions
+pub fn find(recognition: &Recognition, dictionary: &Dictionary, count: usize) -> Option<Candidates> {
+  let mut order: Vec<usize> = (0..recognition.regions.len())
+    .filter(|i| recognition.regions[*i].confidence < 1.0)
+    .collect();
+  order.sort_by(|a, b| recognition.regions[*a].confidence.total_cmp(&recognition.regions[*b].confidence));
+  
+  for index in order {
+    let region = &recognition.regions[index];
+    let mut texts: Vec<String> = Vec::new();
+    let mut alternatives: Vec<_> = region.alternatives.iter().collect();
+    alternatives.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    let alternatives = alternatives.into_iter().map(|alternative| alternative.text.clone());
+    for text in alternatives.chain(dictionary.suggest(&region.text, count)) {
+      if text != region.text && !texts.contains(&text) {
+        texts.push(text);
+      }
+    }
+    texts.truncate(count);
+    
+    if !texts.is_empty() {
+      return Some(Candidates {
+        region: index,
+        texts
+      });
+    }
+  }
+  
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{processor::{Alternative, TextRegion}, shapes::Rect};
+  
+  use super::*;
+  
+  fn dictionary(words: &[&str]) -> Dictionary {
+    Dictionary {
+      words: words.iter().map(|word| word.to_string()).collect()
+    }
+  }
+  
+  #[test]
+  fn suggest_closest_words_first() {
+    let dictionary = dictionary(&["house", "horse", "hose", "mouse", "home", "elephant"]);
+    assert_eq!(dictionary.suggest("hovse", 3), vec!["house", "horse", "hose"]);
+    assert_eq!(dictionary.suggest("hovse", 10).len(), 5);
+  }
+  
+  #[test]
+  fn suggest_leaves_out_the_word_itself_and_far_words() {
+    let dictionary = dictionary(&["cat", "cart", "dog"]);
+    assert_eq!(dictionary.suggest("cat", 5), vec!["cart"]);
+  }
+  
+  #[test]
+  fn suggest_keeps_case() {
+    let dictionary = dictionary(&["hello"]);
+    assert_eq!(dictionary.suggest("Hallo", 1), vec!["Hello"]);
+    assert_eq!(dictionary.suggest("HALLO", 1), vec!["HELLO"]);
+    assert_eq!(dictionary.suggest("hallo", 1), vec!["hello"]);
+  }
+  
+  #[test]
+  fn suggest_only_words() {
+    let dictionary = dictionary(&["hello"]);
+    assert!(dictionary.suggest("hello!", 1).is_empty());
+    assert!(dictionary.suggest("he11o", 1).is_empty());
+    assert!(dictionary.suggest("", 1).is_empty());
+  }
+  
+  #[test]
+  fn edit_distance_stops_at_maximum() {
+    let chars = |word: &str| word.chars().collect::<Vec<char>>();
+    assert_eq!(edit_distance(&chars("kitten"), &chars("sitten")), Some(1));
+    assert_eq!(edit_distance(&chars("kitten"), &chars("sittin")), Some(2));
+    assert_eq!(edit_distance(&chars("kitten"), &chars("sitting")), None);
+    assert_eq!(edit_distance(&chars("a"), &chars("abcd")), None);
+  }
+  
+  #[test]
+  fn find_picks_least_confident_word() {
+    let dictionary = dictionary(&["the", "cat", "sat"]);
+    let mut recognition = Recognition::from_regions(vec![
+      TextRegion::new("tha", 0.8, Rect { x1: 0.0, y1: 0.0, x2: 40.0, y2: 30.0 }),
+      TextRegion::new("cet", 0.4, Rect { x1: 50.0, y1: 0.0, x2: 90.0, y2: 30.0 })
+    ]);
+    recognition.regions[1].alternatives.push(Alternative {
+      text: "cut".to_string(),
+      confidence: 0.3
+    });
+    
+    let candidates = find(&recognition, &dictionary, 3).unwrap();
+    assert_eq!(candidates.region, 1);
+    // Backend's alternatives come first
+    assert_eq!(candidates.texts, vec!["cut", "cat", "sat"]);
+  }
+}
@@ -0,0 +1,341 @@
+use std::{collections::HashMap, path::{Path, PathBuf}};
+
+use serde::Deserialize;
+
+use crate::{action::Action, input::InputSource, shortcut::Shortcut};
+
+// Settings from the config file, command line arguments override them
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  // Name of the recognition backend, see processor::BACKENDS
+  pub backend: Option<String>,
+  // Directory with the Paddle OCR models, searched for if unset
+  pub paddle_models: Option<PathBuf>,
+  // Directory with Tesseract's eng.traineddata, searched for if unset
+  pub tessdata: Option<PathBuf>,
+  // Regions recognized with less confidence (0.0 to 1.0) are low confidence
+  pub confidence_threshold: f32,
+  pub low_confidence: LowConfidence,
+  // Directory with words.txt for the candidate bar, searched for if unset
+  pub dictionary: Option<PathBuf>,
+  // Number of slots in the candidate bar
+  pub candidate_count: usize,
+  // Sources allowed to write and press buttons, e.g. ["pen", "touch"]
+  pub input: Vec<InputSource>,
+  // Touches pressing at least this hard (0.0 to 1.0) are rejected as
+  // palms, off if unset. Only used once the touch screen has reported
+  // different pressures, as many report the same for every touch
+  pub palm_pressure: Option<f32>,
+  // Milliseconds after the last stroke until the text is
+  // submitted by itself, off if unset
+  pub auto_submit_ms: Option<u64>,
+  // Right and left flicks type a space and delete a word
+  pub flick_gestures: bool,
+  // Eraser removes every stroke it touches instead of only the ink under it
+  pub erase_whole_strokes: bool,
+  // Buttons next to the canvas, top to bottom
+  pub buttons: Vec<ButtonConfig>,
+  // Other button bars by name, e.g. one for coding and one for chat
+  pub bars: HashMap<String, Vec<ButtonConfig>>,
+  // Name of the bar in bars used instead of buttons
+  pub bar: Option<String>,
+  // Keys pressed in the writer window, Escape always quits
+  pub shortcuts: Vec<ShortcutConfig>
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ButtonConfig {
+  pub label: String,
+  pub action: Action
+}
+
+impl ButtonConfig {
+  fn new(label: &str, action: Action) -> Self {
+    Self {
+      label: label.to_string(),
+      action
+    }
+  }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ShortcutConfig {
+  pub keys: Shortcut,
+  pub action: Action
+}
+
+impl ShortcutConfig {
+  fn new(keys: &str, action: Action) -> Self {
+    Self {
+      keys: Shortcut::try_from(keys.to_string()).unwrap(),
+      action
+    }
+  }
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      backend: None,
+      paddle_models: None,
+      tessdata: None,
+      confidence_threshold: 0.70,
+      low_confidence: LowConfidence::Drop,
+      dictionary: None,
+      candidate_count: 5,
+      input: vec![InputSource::Pen, InputSource::Mouse, InputSource::Touch],
+      palm_pressure: None,
+      auto_submit_ms: None,
+      flick_gestures: false,
+      erase_whole_strokes: false,
+      buttons: vec![
+        ButtonConfig::new("Clear", Action::Clear),
+        ButtonConfig::new("Submit", Action::Submit),
+        ButtonConfig::new("Enter", Action::Enter),
+        ButtonConfig::new("Space", Action::Space),
+        ButtonConfig::new("Del word", Action::DelWord),
+        ButtonConfig::new("Undo", Action::Undo),
+        ButtonConfig::new("Redo", Action::Redo),
+        ButtonConfig::new("Eraser", Action::Eraser),
+        ButtonConfig::new("Backend", Action::Backend)
+      ],
+      bars: HashMap::new(),
+      bar: None,
+      shortcuts: vec![
+        ShortcutConfig::new("ctrl+return", Action::Submit),
+        ShortcutConfig::new("ctrl+l", Action::Clear),
+        ShortcutConfig::new("ctrl+z", Action::Undo),
+        ShortcutConfig::new("ctrl+shift+z", Action::Redo),
+        ShortcutConfig::new("ctrl+y", Action::Redo),
+        ShortcutConfig::new("ctrl+e", Action::Eraser),
+        ShortcutConfig::new("ctrl+b", Action::Backend)
+      ]
+    }
+  }
+}
+
+// What happens to regions under the confidence threshold
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum LowConfidence {
+  // Left out of the text
+  Drop,
+  // Kept in the text and highlighted on the canvas
+  Keep
+}
+
+impl Config {
+  // Reads the file given by --config, or config.toml in
+  // $XDG_CONFIG_HOME/stylus-writing (~/.config/stylus-writing)
+  pub fn load() -> Self {
+    let mut config_path = None;
+    let mut backend = None;
+    let mut bar = None;
+    
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+      // Both "--name value" and "--name=value" work
+      let (name, value) = match arg.split_once('=') {
+        Some((name, value)) => (name.to_string(), Some(value.to_string())),
+        None => (arg, None)
+      };
+      
+      match name.as_str() {
+        "--config" | "--backend" | "--bar" => {
+          let Some(value) = value.or_else(|| args.next()) else {
+            log::warn!("Missing value for {name}, ignoring");
+            continue;
+          };
+          
+          match name.as_str() {
+            "--config" => config_path = Some(PathBuf::from(value)),
+            "--backend" => backend = Some(value),
+            _ => bar = Some(value)
+          }
+        }
+        _ => log::warn!("Unknown argument '{name}', ignoring")
+      }
+    }
+    
+    let mut config = match &config_path {
+      Some(path) => Self::read(path, true),
+      None => default_path().map(|path| Self::read(&path, false)).unwrap_or_default()
+    };
+    
+    if backend.is_some() {
+      config.backend = backend;
+    }
+    
+    if bar.is_some() {
+      config.bar = bar;
+    }
+    
+    config
+  }
+  
+  // Buttons of the chosen bar, the default ones if it is not defined
+  pub fn button_bar(&self) -> &[ButtonConfig] {
+    let Some(name) = &self.bar else {
+      return &self.buttons;
+    };
+    
+    match self.bars.get(name) {
+      Some(buttons) => buttons,
+      None => {
+        log::warn!("No button bar named '{name}', using the default buttons");
+        &self.buttons
+      }
+    }
+  }
+  
+  // Default config if the file cannot be read, a missing file is
+  // only reported if it was asked for explicitly
+  fn read(path: &Path, is_explicit: bool) -> Self {
+    let content = match std::fs::read_to_string(path) {
+      Ok(content) => content,
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound && !is_explicit => return Self::default(),
+      Err(e) => {
+        log::error!("Error reading config {}: {e}, using defaults", path.display());
+        return Self::default();
+      }
+    };
+    
+    log::info!("Reading config {}", path.display());
+    toml::from_str(&content)
+      .map(Self::validated)
+      .map_err(|e| log::error!("Error in config {}: {e}, using defaults", path.display()))
+      .unwrap_or_default()
+  }
+  
+  // Brings values the file can hold but which make no sense into range
+  fn validated(mut self) -> Self {
+    let threshold = self.confidence_threshold;
+    if threshold.is_nan() {
+      self.confidence_threshold = Self::default().confidence_threshold;
+      log::warn!("confidence_threshold is not a number, using {}", self.confidence_threshold);
+    } else if !(0.0..=1.0).contains(&threshold) {
+      self.confidence_threshold = threshold.clamp(0.0, 1.0);
+      log::warn!("confidence_threshold {threshold} is not between 0.0 and 1.0, using {}", self.confidence_threshold);
+    }
+    
+    self
+  }
+}
+
+fn default_path() -> Option<PathBuf> {
+  let config_home = std::env::var_os("XDG_CONFIG_HOME")
+    .filter(|value| !value.is_empty())
+    .map(PathBuf::from)
+    .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+  
+  Some(config_home.join("stylus-writing").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  fn parse(content: &str) -> Config {
+    toml::from_str(content).unwrap()
+  }
+  
+  #[test]
+  fn empty_config_is_default() {
+    let config = parse("");
+    assert_eq!(config.confidence_threshold, 0.70);
+    assert_eq!(config.low_confidence, LowConfidence::Drop);
+    assert_eq!(config.candidate_count, 5);
+    assert_eq!(config.buttons.len(), 9);
+    assert_eq!(config.shortcuts.len(), 7);
+    assert!(!config.erase_whole_strokes);
+  }
+  
+  #[test]
+  fn set_fields_keep_defaults_for_others() {
+    let config = parse("
+      backend = \"tesseract\"
+      low_confidence = \"keep\"
+      input = [\"pen\", \"touch\"]
+      palm_pressure = 0.8
+      auto_submit_ms = 1500
+    ");
+    assert_eq!(config.backend.as_deref(), Some("tesseract"));
+    assert_eq!(config.low_confidence, LowConfidence::Keep);
+    assert_eq!(config.input, vec![InputSource::Pen, InputSource::Touch]);
+    assert_eq!(config.palm_pressure, Some(0.8));
+    assert_eq!(config.auto_submit_ms, Some(1500));
+    assert_eq!(config.confidence_threshold, 0.70);
+    assert_eq!(config.buttons.len(), 9);
+  }
+  
+  #[test]
+  fn buttons_take_every_kind_of_action() {
+    let config = parse("
+      [[buttons]]
+      label = \"Undo\"
+      action = \"undo\"
+      
+      [[buttons]]
+      label = \"Tab\"
+      action = { keys = \"shift+tab\" }
+      
+      [[buttons]]
+      label = \"Main\"
+      action = { text = \"fn main() {}\" }
+    ");
+    let actions: Vec<&Action> = config.buttons.iter().map(|button| &button.action).collect();
+    assert_eq!(actions[0], &Action::Undo);
+    assert!(matches!(actions[1], Action::Keys(keys) if keys.0.len() == 2));
+    assert_eq!(actions[2], &Action::Text("fn main() {}".to_string()));
+    assert_eq!(config.buttons[1].label, "Tab");
+  }
+  
+  #[test]
+  fn bar_is_chosen_by_name() {
+    let mut config = parse("
+      bar = \"chat\"
+      
+      [[bars.chat]]
+      label = \"Send\"
+      action = \"submit\"
+    ");
+    assert_eq!(config.button_bar().len(), 1);
+    assert_eq!(config.button_bar()[0].action, Action::Submit);
+    
+    // Unknown bars fall back to the default buttons
+    config.bar = Some("code".to_string());
+    assert_eq!(config.button_bar().len(), 9);
+  }
+  
+  #[test]
+  fn shortcuts_replace_defaults() {
+    let config = parse("
+      [[shortcuts]]
+      keys = \"ctrl+enter\"
+      action = \"submit\"
+    ");
+    assert_eq!(config.shortcuts.len(), 1);
+    assert_eq!(config.shortcuts[0].action, Action::Submit);
+  }
+  
+  #[test]
+  fn confidence_threshold_is_brought_into_range() {
+    assert_eq!(parse("confidence_threshold = 0.5").validated().confidence_threshold, 0.5);
+    assert_eq!(parse("confidence_threshold = 70.0").validated().confidence_threshold, 1.0);
+    assert_eq!(parse("confidence_threshold = -0.2").validated().confidence_threshold, 0.0);
+    assert_eq!(parse("confidence_threshold = nan").validated().confidence_threshold, 0.70);
+  }
+  
+  #[test]
+  fn mistakes_are_rejected() {
+    assert!(toml::from_str::<Config>("confidence_treshold = 0.5").is_err());
+    assert!(toml::from_str::<Config>("low_confidence = \"hide\"").is_err());
+    assert!(toml::from_str::<Config>("input = [\"trackpad\"]").is_err());
+    assert!(toml::from_str::<Config>("[[buttons]]\nlabel = \"Go\"\naction = \"jump\"").is_err());
+    assert!(toml::from_str::<Config>("[[buttons]]\nlabel = \"Go\"\naction = \"submit\"\ncolor = \"red\"").is_err());
+    assert!(toml::from_str::<Config>("[[shortcuts]]\nkeys = \"hyper+z\"\naction = \"undo\"").is_err());
+  }
+}
@@ -0,0 +1,114 @@
+use std::{ffi::OsString, path::{Path, PathBuf}};
+
+// Directory under the data dirs this program's files are kept in
+const APP_DIR: &str = "stylus-writing";
+
+// Finds the first directory named dir_name which has all the files in it.
+// Looked for in order: env_var, configured, $XDG_DATA_HOME, $XDG_DATA_DIRS,
+// next to the executable and the working directory.
+// Gives back every directory tried if none has the files
+pub fn find(dir_name: &str, files: &[&str], env_var: &str, configured: Option<&Path>) -> Result<PathBuf, Vec<PathBuf>> {
+  first_with_files(candidates(dir_name, env_var, configured, |name| std::env::var_os(name)), files)
+}
+
+// Directories to look in, best first, env gives the value of a variable
+fn candidates(dir_name: &str, env_var: &str, configured: Option<&Path>, env: impl Fn(&str) -> Option<OsString>) -> Vec<PathBuf> {
+  let env = |name: &str| env(name).filter(|value| !value.is_empty());
+  let mut candidates = Vec::new();
+  
+  if let Some(value) = env(env_var) {
+    candidates.push(PathBuf::from(value));
+  }
+  
+  if let Some(configured) = configured {
+    candidates.push(configured.to_path_buf());
+  }
+  
+  let data_home = env("XDG_DATA_HOME")
+    .map(PathBuf::from)
+    .or_else(|| env("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+  candidates.extend(data_home.map(|dir| dir.join(APP_DIR).join(dir_name)));
+  
+  let data_dirs = env("XDG_DATA_DIRS")
+    .map(|value| value.to_string_lossy().into_owned())
+    .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+  for dir in data_dirs.split(':').filter(|dir| !dir.is_empty()) {
+    candidates.push(Path::new(dir).join(APP_DIR).join(dir_name));
+  }
+  
+  if let Some(exe_dir) = std::env::current_exe().ok().as_deref().and_then(Path::parent) {
+    candidates.push(exe_dir.join(dir_name));
+  }
+  
+  candidates.push(PathBuf::from(".").join(dir_name));
+  candidates
+}
+
+fn first_with_files(candidates: Vec<PathBuf>, files: &[&str]) -> Result<PathBuf, Vec<PathBuf>> {
+  match candidates.iter().find(|dir| files.iter().all(|file| dir.join(file).is_file())) {
+    Some(dir) => Ok(dir.clone()),
+    None => Err(candidates)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+  
+  use super::*;
+  
+  fn candidates_with(vars: &[(&str, &str)], configured: Option<&Path>) -> Vec<PathBuf> {
+    let vars: HashMap<&str, &str> = vars.iter().copied().collect();
+    candidates("models", "MODELS", configured, |name| vars.get(name).map(OsString::from))
+  }
+  
+  #[test]
+  fn candidates_are_in_order() {
+    let candidates = candidates_with(&[
+      ("MODELS", "/env/models"),
+      ("XDG_DATA_HOME", "/data-home"),
+      ("HOME", "/home/user"),
+      ("XDG_DATA_DIRS", "/first:/second")
+    ], Some(Path::new("/configured")));
+    
+    let exe_dir = std::env::current_exe().unwrap().parent().unwrap().join("models");
+    assert_eq!(candidates, vec![
+      PathBuf::from("/env/models"),
+      PathBuf::from("/configured"),
+      PathBuf::from("/data-home/stylus-writing/models"),
+      PathBuf::from("/first/stylus-writing/models"),
+      PathBuf::from("/second/stylus-writing/models"),
+      exe_dir,
+      PathBuf::from("./models")
+    ]);
+  }
+  
+  #[test]
+  fn unset_and_empty_variables_use_defaults() {
+    let candidates = candidates_with(&[("MODELS", ""), ("XDG_DATA_HOME", ""), ("HOME", "/home/user")], None);
+    assert_eq!(candidates[..3], [
+      PathBuf::from("/home/user/.local/share/stylus-writing/models"),
+      PathBuf::from("/usr/local/share/stylus-writing/models"),
+      PathBuf::from("/usr/share/stylus-writing/models")
+    ]);
+  }
+  
+  #[test]
+  fn first_directory_with_every_file_is_found() {
+    let root = std::env::temp_dir().join(format!("stylus-writing-data-dir-{}", std::process::id()));
+    let (partial, complete, later) = (root.join("partial"), root.join("complete"), root.join("later"));
+    for dir in [&partial, &complete, &later] {
+      std::fs::create_dir_all(dir).unwrap();
+      std::fs::write(dir.join("a.onnx"), "").unwrap();
+    }
+    std::fs::write(complete.join("b.onnx"), "").unwrap();
+    std::fs::write(later.join("b.onnx"), "").unwrap();
+    
+    let found = first_with_files(vec![root.join("missing"), partial.clone(), complete.clone(), later.clone()], &["a.onnx", "b.onnx"]);
+    let not_found = first_with_files(vec![partial.clone()], &["a.onnx", "c.onnx"]);
+    std::fs::remove_dir_all(&root).unwrap();
+    
+    assert_eq!(found, Ok(complete));
+    assert_eq!(not_found, Err(vec![partial]));
+  }
+}
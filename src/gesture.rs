@@ -0,0 +1,303 @@
+use crate::shapes::{Point, Stroke};
+
+// Movement back smaller than this is jitter, not a change of direction
+const REVERSAL_THRESHOLD: f32 = 6.0;
+
+// A scratch-out goes back and forth at least this many times
+const SCRATCH_OUT_MIN_REVERSALS: usize = 4;
+
+// Ink length compared to the longer side of the scribble, a
+// zig-zag covers the same area over and over
+const SCRATCH_OUT_MIN_DENSITY: f32 = 3.0;
+
+// Each pass back and forth goes at least this part of the longer
+// side, handwriting like "mmm" only goes back a little
+const SCRATCH_OUT_MIN_PASS: f32 = 0.5;
+
+// Times the scribble must cross a stroke to scratch it out
+const SCRATCH_OUT_MIN_CROSSINGS: usize = 2;
+
+// Part of a stroke's points which must be under the scribble
+// for the stroke to be scratched out
+const SCRATCH_OUT_MIN_COVERAGE: f32 = 0.6;
+
+// Flicks must be at least this long and done within the time
+const FLICK_MIN_LENGTH: f32 = 120.0;
+const FLICK_MAX_DURATION_NS: u64 = 400_000_000;
+
+// Straight distance compared to the ink length, 1.0 is perfectly straight
+const MIN_STRAIGHTNESS: f32 = 0.85;
+
+// Each leg of the hook
+const HOOK_MIN_LEG_LENGTH: f32 = 30.0;
+
+// Strokes which edit the text instead of being written
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EditGesture {
+  // Long right flick
+  Space,
+  // Left flick
+  DelWord,
+  // Down then left, like the return key symbol
+  Enter
+}
+
+pub fn detect_edit_gesture(stroke: &Stroke) -> Option<EditGesture> {
+  let first = stroke.points.first()?;
+  let last = stroke.points.last()?;
+  
+  if is_straight(stroke, 0, stroke.points.len() - 1) {
+    let dx = last.point.x - first.point.x;
+    let dy = last.point.y - first.point.y;
+    let duration = last.timestamp.saturating_sub(first.timestamp);
+    if dx.abs() >= FLICK_MIN_LENGTH && dy.abs() <= dx.abs() * 0.3 && duration <= FLICK_MAX_DURATION_NS {
+      return Some(if dx > 0.0 { EditGesture::Space } else { EditGesture::DelWord });
+    }
+    
+    return None;
+  }
+  
+  // Corner of the hook is the point furthest from the line between the ends
+  let (corner, _) = stroke.points.iter()
+    .enumerate()
+    .map(|(i, point)| (i, point.point.distance_to_segment(&first.point, &last.point)))
+    .max_by(|a, b| a.1.total_cmp(&b.1))?;
+  
+  if !is_straight(stroke, 0, corner) || !is_straight(stroke, corner, stroke.points.len() - 1) {
+    return None;
+  }
+  
+  let corner = &stroke.points[corner].point;
+  let is_down = |start: &Point, end: &Point| {
+    let dx = end.x - start.x;
+    let dy = end.y - start.y;
+    dy >= HOOK_MIN_LEG_LENGTH && dx.abs() <= dy * 0.5
+  };
+  let is_left = |start: &Point, end: &Point| {
+    let dx = start.x - end.x;
+    let dy = end.y - start.y;
+    dx >= HOOK_MIN_LEG_LENGTH && dy.abs() <= dx * 0.5
+  };
+  
+  if is_down(&first.point, corner) && is_left(corner, &last.point) {
+    return Some(EditGesture::Enter);
+  }
+  
+  None
+}
+
+// Whether the points from start to end index go in a straight line
+fn is_straight(stroke: &Stroke, start: usize, end: usize) -> bool {
+  let points = &stroke.points[start..=end];
+  let length: f32 = points.windows(2)
+    .map(|pair| pair[0].point.distance(&pair[1].point))
+    .sum();
+  
+  if length <= 0.0 {
+    return false;
+  }
+  
+  points[0].point.distance(&points[points.len() - 1].point) / length >= MIN_STRAIGHTNESS
+}
+
+// Back and forth along the longer side of the scribble, with
+// every pass going over most of it
+pub fn is_scratch_out(stroke: &Stroke) -> bool {
+  let bound = stroke.bounding_box();
+  let width = bound.x2 - bound.x1;
+  let height = bound.y2 - bound.y1;
+  let longest_side = f32::max(width, height);
+  if longest_side <= 0.0 {
+    return false;
+  }
+  
+  let min_pass = longest_side * SCRATCH_OUT_MIN_PASS;
+  let reversals = if width >= height {
+    count_reversals(stroke.points.iter().map(|point| point.point.x), min_pass)
+  } else {
+    count_reversals(stroke.points.iter().map(|point| point.point.y), min_pass)
+  };
+  
+  reversals >= SCRATCH_OUT_MIN_REVERSALS && stroke.length() / longest_side >= SCRATCH_OUT_MIN_DENSITY
+}
+
+// Whether the stroke is mostly under the scribble and the
+// scribble goes over its ink, not only around it
+pub fn is_scratched_by(stroke: &Stroke, scribble: &Stroke) -> bool {
+  let bound = scribble.bounding_box();
+  let covered = stroke.points.iter()
+    .filter(|point| bound.contains(&point.point))
+    .count();
+  
+  if (covered as f32) < stroke.points.len() as f32 * SCRATCH_OUT_MIN_COVERAGE {
+    return false;
+  }
+  
+  count_crossings(stroke, scribble) >= SCRATCH_OUT_MIN_CROSSINGS
+}
+
+// Counts how many times the values change direction after
+// going at least min_pass in one direction
+fn count_reversals(values: impl Iterator<Item = f32>, min_pass: f32) -> usize {
+  let mut reversals = 0;
+  // .0 = furthest value reached in current direction
+  // .1 = whether current direction is increasing
+  // .2 = where the current direction started
+  let mut extreme: Option<(f32, Option<bool>, f32)> = None;
+  
+  for value in values {
+    let Some((furthest, increasing, pass_start)) = extreme.as_mut() else {
+      extreme = Some((value, None, value));
+      continue;
+    };
+    
+    match *increasing {
+      None if (value - *furthest).abs() >= REVERSAL_THRESHOLD => {
+        *increasing = Some(value > *furthest);
+        *furthest = value;
+      }
+      None => (),
+      Some(true) if value > *furthest => *furthest = value,
+      Some(false) if value < *furthest => *furthest = value,
+      Some(direction) if (value - *furthest).abs() >= REVERSAL_THRESHOLD => {
+        if (*furthest - *pass_start).abs() >= min_pass {
+          reversals += 1;
+        }
+        *increasing = Some(!direction);
+        *pass_start = *furthest;
+        *furthest = value;
+      }
+      Some(_) => ()
+    }
+  }
+  
+  reversals
+}
+
+fn count_crossings(stroke: &Stroke, scribble: &Stroke) -> usize {
+  let mut crossings = 0;
+  for pair in stroke.points.windows(2) {
+    for other in scribble.points.windows(2) {
+      if segments_cross(&pair[0].point, &pair[1].point, &other[0].point, &other[1].point) {
+        crossings += 1;
+      }
+    }
+  }
+  
+  crossings
+}
+
+fn segments_cross(a1: &Point, a2: &Point, b1: &Point, b2: &Point) -> bool {
+  // Which side of the line through start and end the point is on
+  let side = |start: &Point, end: &Point, point: &Point| {
+    (end.x - start.x) * (point.y - start.y) - (end.y - start.y) * (point.x - start.x)
+  };
+  
+  let d1 = side(b1, b2, a1);
+  let d2 = side(b1, b2, a2);
+  let d3 = side(a1, a2, b1);
+  let d4 = side(a1, a2, b2);
+  d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{input::PointerId, shapes::InkPoint};
+  
+  // Points 10ms apart
+  fn stroke(points: &[(f32, f32)]) -> Stroke {
+    let ink_point = |index: usize, (x, y): (f32, f32)| InkPoint {
+      point: Point { x, y },
+      timestamp: index as u64 * 10_000_000,
+      pressure: 0.5,
+      tilt: None
+    };
+    
+    let mut stroke = Stroke::new(PointerId::pen(0), ink_point(0, points[0]));
+    for (index, point) in points.iter().enumerate().skip(1) {
+      stroke.push(ink_point(index, *point));
+    }
+    stroke
+  }
+  
+  fn scribble() -> Stroke {
+    stroke(&[(0.0, 40.0), (100.0, 60.0), (0.0, 40.0), (100.0, 60.0), (0.0, 40.0), (100.0, 60.0)])
+  }
+  
+  #[test]
+  fn back_and_forth_is_scratch_out() {
+    assert!(is_scratch_out(&scribble()));
+  }
+  
+  #[test]
+  fn cursive_m_is_not_scratch_out() {
+    // Humps going right, each going back a little at the bottom
+    let mut points = Vec::new();
+    for hump in 0..6 {
+      let x = hump as f32 * 12.0;
+      points.push((x, 20.0));
+      points.push((x + 10.0, 0.0));
+      points.push((x + 4.0, 20.0));
+    }
+    assert!(!is_scratch_out(&stroke(&points)));
+  }
+  
+  #[test]
+  fn line_is_not_scratch_out() {
+    assert!(!is_scratch_out(&stroke(&[(0.0, 0.0), (50.0, 0.0), (100.0, 0.0)])));
+  }
+  
+  #[test]
+  fn stroke_crossed_by_scribble_is_scratched() {
+    let word = stroke(&[(20.0, 50.0), (45.0, 50.0), (80.0, 50.0)]);
+    assert!(is_scratched_by(&word, &scribble()));
+  }
+  
+  #[test]
+  fn stroke_only_inside_scribble_bound_is_not_scratched() {
+    let scribble = stroke(&[(0.0, 40.0), (100.0, 40.0), (0.0, 40.0), (100.0, 40.0), (0.0, 40.0), (100.0, 80.0)]);
+    let word = stroke(&[(10.0, 70.0), (15.0, 70.0), (20.0, 70.0)]);
+    assert!(!is_scratched_by(&word, &scribble));
+  }
+  
+  #[test]
+  fn stroke_outside_scribble_is_not_scratched() {
+    let word = stroke(&[(120.0, 50.0), (150.0, 50.0), (180.0, 50.0)]);
+    assert!(!is_scratched_by(&word, &scribble()));
+  }
+  
+  #[test]
+  fn right_flick_is_space() {
+    assert_eq!(detect_edit_gesture(&stroke(&[(0.0, 0.0), (75.0, 2.0), (150.0, 4.0)])), Some(EditGesture::Space));
+  }
+  
+  #[test]
+  fn left_flick_is_delword() {
+    assert_eq!(detect_edit_gesture(&stroke(&[(150.0, 0.0), (75.0, 0.0), (0.0, 0.0)])), Some(EditGesture::DelWord));
+  }
+  
+  #[test]
+  fn slow_line_is_not_flick() {
+    // 500ms
+    let points: Vec<_> = (0..=50).map(|i| (i as f32 * 3.0, 0.0)).collect();
+    assert_eq!(detect_edit_gesture(&stroke(&points)), None);
+  }
+  
+  #[test]
+  fn short_line_is_not_flick() {
+    assert_eq!(detect_edit_gesture(&stroke(&[(0.0, 0.0), (40.0, 0.0), (80.0, 0.0)])), None);
+  }
+  
+  #[test]
+  fn down_then_left_is_enter() {
+    let hook = stroke(&[(100.0, 0.0), (100.0, 40.0), (100.0, 80.0), (60.0, 80.0), (20.0, 80.0)]);
+    assert_eq!(detect_edit_gesture(&hook), Some(EditGesture::Enter));
+  }
+  
+  #[test]
+  fn down_then_right_is_not_enter() {
+    let hook = stroke(&[(20.0, 0.0), (20.0, 40.0), (20.0, 80.0), (60.0, 80.0), (100.0, 80.0)]);
+    assert_eq!(detect_edit_gesture(&hook), None);
+  }
+}
@@ -0,0 +1,85 @@
+use sdl3::{pixels::Color, render::{BlendMode, Canvas}, video::Window};
+
+use crate::{ink::InkMesh, shapes::{Point, Rect}};
+
+// Line width compared to the icon size
+const LINE_WIDTH: f32 = 0.1;
+
+// Small line drawings shown on buttons
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Icon {
+  Clear,
+  Submit,
+  Enter,
+  Space,
+  DelWord,
+  Undo,
+  Redo,
+  Eraser,
+  Backend
+}
+
+impl Icon {
+  // Polylines in a unit square, (0, 0) is the top left
+  fn lines(&self) -> Vec<Vec<(f32, f32)>> {
+    match self {
+      Icon::Clear => vec![
+        vec![(0.15, 0.15), (0.85, 0.85)],
+        vec![(0.85, 0.15), (0.15, 0.85)]
+      ],
+      Icon::Submit => vec![
+        vec![(0.1, 0.55), (0.4, 0.85), (0.9, 0.2)]
+      ],
+      Icon::Enter => vec![
+        vec![(0.85, 0.15), (0.85, 0.65), (0.15, 0.65)],
+        vec![(0.35, 0.45), (0.15, 0.65), (0.35, 0.85)]
+      ],
+      Icon::Space => vec![
+        vec![(0.1, 0.5), (0.1, 0.75), (0.9, 0.75), (0.9, 0.5)]
+      ],
+      Icon::DelWord => vec![
+        vec![(0.05, 0.5), (0.3, 0.2), (0.95, 0.2), (0.95, 0.8), (0.3, 0.8), (0.05, 0.5)],
+        vec![(0.45, 0.35), (0.75, 0.65)],
+        vec![(0.75, 0.35), (0.45, 0.65)]
+      ],
+      Icon::Undo => vec![
+        vec![(0.3, 0.25), (0.7, 0.25), (0.85, 0.4), (0.85, 0.6), (0.7, 0.75), (0.3, 0.75)],
+        vec![(0.45, 0.1), (0.3, 0.25), (0.45, 0.4)]
+      ],
+      Icon::Redo => vec![
+        vec![(0.7, 0.25), (0.3, 0.25), (0.15, 0.4), (0.15, 0.6), (0.3, 0.75), (0.7, 0.75)],
+        vec![(0.55, 0.1), (0.7, 0.25), (0.55, 0.4)]
+      ],
+      Icon::Eraser => vec![
+        vec![(0.1, 0.6), (0.5, 0.2), (0.9, 0.6), (0.6, 0.9), (0.4, 0.9), (0.1, 0.6)],
+        vec![(0.3, 0.4), (0.7, 0.8)]
+      ],
+      Icon::Backend => vec![
+        vec![(0.1, 0.35), (0.9, 0.35)],
+        vec![(0.7, 0.15), (0.9, 0.35), (0.7, 0.55)],
+        vec![(0.9, 0.65), (0.1, 0.65)],
+        vec![(0.3, 0.45), (0.1, 0.65), (0.3, 0.85)]
+      ]
+    }
+  }
+  
+  // Drawn as large as fits in the bound, centered
+  pub fn draw(&self, canvas: &mut Canvas<Window>, bound: &Rect, color: Color) {
+    let size = f32::min(bound.x2 - bound.x1, bound.y2 - bound.y1);
+    let x = (bound.x1 + bound.x2 - size) / 2.0;
+    let y = (bound.y1 + bound.y2 - size) / 2.0;
+    
+    let mut mesh = InkMesh::new(color.into());
+    for line in self.lines() {
+      let points: Vec<(Point, f32)> = line.iter()
+        .map(|(px, py)| (Point { x: x + px * size, y: y + py * size }, size * LINE_WIDTH))
+        .collect();
+      mesh.add_polyline(&points, &points);
+    }
+    
+    canvas.set_blend_mode(BlendMode::Blend);
+    let _ = canvas.render_geometry(&mesh.vertices, None, mesh.indices.as_slice())
+      .map_err(|e| log::warn!("error calling canvas.render_geometry: {e}"));
+    canvas.set_blend_mode(BlendMode::None);
+  }
+}
@@ -0,0 +1,216 @@
+use std::f32::consts::TAU;
+
+use sdl3::{pixels::FColor, render::{FPoint, Vertex}};
+
+use crate::shapes::{InkPoint, Point};
+
+// Width of the fading edge, which makes the ink anti-aliased
+const FRINGE_WIDTH: f32 = 1.0;
+
+// Longest distance between two smoothed points
+const SMOOTH_STEP: f32 = 2.0;
+
+// Catmull-Rom spline through the points, returns the points
+// on the curve with their pressure
+pub fn smooth(points: &[InkPoint]) -> Vec<(Point, f32)> {
+  if points.len() < 3 {
+    return points.iter()
+      .map(|point| (point.point.clone(), point.pressure))
+      .collect();
+  }
+  
+  let mut result = Vec::new();
+  for i in 0..(points.len() - 1) {
+    // The spline needs one point before and after the segment,
+    // the ends reuse the first and last points
+    let p0 = &points[i.saturating_sub(1)].point;
+    let p1 = &points[i].point;
+    let p2 = &points[i + 1].point;
+    let p3 = &points[usize::min(i + 2, points.len() - 1)].point;
+    let pressure1 = points[i].pressure;
+    let pressure2 = points[i + 1].pressure;
+    
+    let steps = (p1.distance(p2) / SMOOTH_STEP).ceil().max(1.0) as usize;
+    for step in 0..steps {
+      let t = step as f32 / steps as f32;
+      let t2 = t * t;
+      let t3 = t2 * t;
+      let at = |v0: f32, v1: f32, v2: f32, v3: f32| {
+        0.5 * (
+          2.0 * v1 +
+          (v2 - v0) * t +
+          (2.0 * v0 - 5.0 * v1 + 4.0 * v2 - v3) * t2 +
+          (3.0 * v1 - v0 - 3.0 * v2 + v3) * t3
+        )
+      };
+      
+      result.push((
+        Point {
+          x: at(p0.x, p1.x, p2.x, p3.x),
+          y: at(p0.y, p1.y, p2.y, p3.y)
+        },
+        pressure1 + (pressure2 - pressure1) * t
+      ));
+    }
+  }
+  
+  let last = points.last().unwrap();
+  result.push((last.point.clone(), last.pressure));
+  result
+}
+
+// Triangles for round capped and joined ink
+pub struct InkMesh {
+  pub vertices: Vec<Vertex>,
+  pub indices: Vec<u32>,
+  color: FColor
+}
+
+impl InkMesh {
+  pub fn new(color: FColor) -> Self {
+    Self {
+      vertices: Vec::new(),
+      indices: Vec::new(),
+      color
+    }
+  }
+  
+  // Points are the centerline and each point's width, joins are the ends
+  // and where it may bend sharply. Discs at joins make the caps and joins
+  // round, between them it bends too little to need them
+  pub fn add_polyline(&mut self, points: &[(Point, f32)], joins: &[(Point, f32)]) {
+    for (point, width) in joins {
+      self.add_disc(point, width / 2.0);
+    }
+    
+    for pair in points.windows(2) {
+      let ((start, start_width), (end, end_width)) = (&pair[0], &pair[1]);
+      self.add_segment(start, start_width / 2.0, end, end_width / 2.0);
+    }
+  }
+  
+  fn push_vertex(&mut self, x: f32, y: f32, alpha: f32) -> u32 {
+    let index = self.vertices.len() as u32;
+    self.vertices.push(Vertex {
+      position: FPoint { x, y },
+      color: FColor { a: self.color.a * alpha, ..self.color },
+      tex_coord: FPoint { x: 0.0, y: 0.0 }
+    });
+    index
+  }
+  
+  fn add_disc(&mut self, center: &Point, radius: f32) {
+    // Solid part is shrunk so the fringe does not make ink wider
+    let inner = (radius - FRINGE_WIDTH / 2.0).max(0.0);
+    let outer = inner + FRINGE_WIDTH;
+    let sides = (outer * 2.0).clamp(8.0, 32.0) as u32;
+    
+    let center_index = self.push_vertex(center.x, center.y, 1.0);
+    let first = self.vertices.len() as u32;
+    for side in 0..sides {
+      let angle = TAU * side as f32 / sides as f32;
+      let (sin, cos) = angle.sin_cos();
+      self.push_vertex(center.x + cos * inner, center.y + sin * inner, 1.0);
+      self.push_vertex(center.x + cos * outer, center.y + sin * outer, 0.0);
+    }
+    
+    for side in 0..sides {
+      let inner_a = first + side * 2;
+      let outer_a = inner_a + 1;
+      let inner_b = first + ((side + 1) % sides) * 2;
+      let outer_b = inner_b + 1;
+      self.indices.extend_from_slice(&[
+        center_index, inner_a, inner_b,
+        inner_a, outer_a, outer_b,
+        inner_a, outer_b, inner_b
+      ]);
+    }
+  }
+  
+  fn add_segment(&mut self, start: &Point, start_radius: f32, end: &Point, end_radius: f32) {
+    let dx = end.x - start.x;
+    let dy = end.y - start.y;
+    let length = f32::sqrt(dx * dx + dy * dy);
+    if length == 0.0 {
+      return;
+    }
+    
+    // Unit normal of the segment
+    let nx = -dy / length;
+    let ny = dx / length;
+    
+    // Across each end: outer left, inner left, inner right, outer right
+    let mut across = |point: &Point, radius: f32| {
+      let inner = (radius - FRINGE_WIDTH / 2.0).max(0.0);
+      let outer = inner + FRINGE_WIDTH;
+      [
+        self.push_vertex(point.x + nx * outer, point.y + ny * outer, 0.0),
+        self.push_vertex(point.x + nx * inner, point.y + ny * inner, 1.0),
+        self.push_vertex(point.x - nx * inner, point.y - ny * inner, 1.0),
+        self.push_vertex(point.x - nx * outer, point.y - ny * outer, 0.0)
+      ]
+    };
+    let a = across(start, start_radius);
+    let b = across(end, end_radius);
+    
+    for i in 0..3 {
+      self.indices.extend_from_slice(&[
+        a[i], a[i + 1], b[i],
+        a[i + 1], b[i + 1], b[i]
+      ]);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  fn ink_point(x: f32, y: f32, pressure: f32) -> InkPoint {
+    InkPoint {
+      point: Point { x, y },
+      timestamp: 0,
+      pressure,
+      tilt: None
+    }
+  }
+  
+  #[test]
+  fn smooth_keeps_short_strokes() {
+    let smoothed = smooth(&[ink_point(1.0, 2.0, 0.5), ink_point(3.0, 4.0, 0.7)]);
+    assert_eq!(smoothed.len(), 2);
+    assert_eq!((smoothed[1].0.x, smoothed[1].0.y, smoothed[1].1), (3.0, 4.0, 0.7));
+  }
+  
+  #[test]
+  fn smooth_passes_through_every_point() {
+    let points = [ink_point(0.0, 0.0, 0.2), ink_point(10.0, 5.0, 0.4), ink_point(20.0, 0.0, 0.6), ink_point(30.0, 8.0, 0.8)];
+    let smoothed = smooth(&points);
+    for point in points.iter() {
+      assert!(
+        smoothed.iter().any(|(smoothed, pressure)| smoothed.distance(&point.point) < 1e-4 && (pressure - point.pressure).abs() < 1e-4),
+        "{} is not on the curve", point.point
+      );
+    }
+  }
+  
+  #[test]
+  fn smooth_steps_are_short() {
+    let points = [ink_point(0.0, 0.0, 0.5), ink_point(50.0, 0.0, 0.5), ink_point(50.0, 50.0, 0.5)];
+    let smoothed = smooth(&points);
+    for pair in smoothed.windows(2) {
+      // Curve bulges a bit past the straight step length
+      assert!(pair[0].0.distance(&pair[1].0) <= SMOOTH_STEP * 1.5);
+    }
+  }
+  
+  #[test]
+  fn smooth_interpolates_pressure() {
+    let smoothed = smooth(&[ink_point(0.0, 0.0, 0.0), ink_point(10.0, 0.0, 1.0), ink_point(20.0, 0.0, 0.0)]);
+    let pressures: Vec<f32> = smoothed.iter().map(|(_, pressure)| *pressure).collect();
+    // Rises to the middle point and falls after it
+    let peak = pressures.iter().position(|pressure| *pressure == 1.0).unwrap();
+    assert!(pressures[..=peak].windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(pressures[peak..].windows(2).all(|pair| pair[0] > pair[1]));
+  }
+}
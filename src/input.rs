@@ -0,0 +1,152 @@
+use sdl3::{event::Event, mouse::MouseButton};
+use serde::Deserialize;
+
+use crate::{config::Config, shapes::Point, window::Window};
+
+// Mouse and touch events SDL synthesizes from other devices,
+// these are already handled as their original source
+const TOUCH_MOUSE_ID: u32 = u32::MAX;
+const PEN_MOUSE_ID: u32 = u32::MAX - 1;
+const MOUSE_TOUCH_ID: u64 = u64::MAX;
+const PEN_TOUCH_ID: u64 = u64::MAX - 1;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum InputSource {
+  Pen,
+  Mouse,
+  Touch
+}
+
+// Identifies one pen, mouse or finger
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct PointerId {
+  pub source: InputSource,
+  // Touch device of a finger, finger ids are only unique per device
+  pub device: u64,
+  pub id: u64
+}
+
+impl PointerId {
+  pub fn pen(which: u32) -> Self {
+    Self {
+      source: InputSource::Pen,
+      device: 0,
+      id: which as u64
+    }
+  }
+}
+
+// Pen, mouse and touch events in window coordinates
+pub enum PointerEvent {
+  Down {
+    pointer: PointerId,
+    x: f32,
+    y: f32,
+    // SDL event timestamp in nanoseconds
+    timestamp: u64,
+    eraser: bool,
+    // Only reported by touch, larger contacts usually report more
+    pressure: Option<f32>
+  },
+  Up {
+    pointer: PointerId,
+    x: f32,
+    y: f32,
+    timestamp: u64
+  },
+  Motion {
+    pointer: PointerId,
+    x: f32,
+    y: f32,
+    timestamp: u64
+  }
+}
+
+impl PointerEvent {
+  pub fn pointer(&self) -> PointerId {
+    match self {
+      PointerEvent::Down { pointer, .. } => *pointer,
+      PointerEvent::Up { pointer, .. } => *pointer,
+      PointerEvent::Motion { pointer, .. } => *pointer
+    }
+  }
+  
+  pub fn position(&self) -> Point {
+    match self {
+      PointerEvent::Down { x, y, .. } => Point { x: *x, y: *y },
+      PointerEvent::Up { x, y, .. } => Point { x: *x, y: *y },
+      PointerEvent::Motion { x, y, .. } => Point { x: *x, y: *y }
+    }
+  }
+  
+  // None if the event is not from a pen, mouse or finger
+  pub fn from_sdl(event: &Event, window: &Window) -> Option<Self> {
+    // Finger positions are normalized to the window size
+    let width = window.get_width() as f32;
+    let height = window.get_height() as f32;
+    let finger = |touch_id: u64, finger_id: u64| PointerId {
+      source: InputSource::Touch,
+      device: touch_id,
+      id: finger_id
+    };
+    let mouse = |which: u32| PointerId {
+      source: InputSource::Mouse,
+      device: 0,
+      id: which as u64
+    };
+    
+    let translated = match *event {
+      Event::PenDown { timestamp, which, x, y, eraser, .. } => PointerEvent::Down { pointer: PointerId::pen(which), x, y, timestamp, eraser, pressure: None },
+      Event::PenUp { timestamp, which, x, y, .. } => PointerEvent::Up { pointer: PointerId::pen(which), x, y, timestamp },
+      Event::PenMotion { timestamp, which, x, y, .. } => PointerEvent::Motion { pointer: PointerId::pen(which), x, y, timestamp },
+      
+      Event::MouseButtonDown { which, .. } |
+      Event::MouseButtonUp { which, .. } |
+      Event::MouseMotion { which, .. } if which == TOUCH_MOUSE_ID || which == PEN_MOUSE_ID => return None,
+      Event::MouseButtonDown { timestamp, which, mouse_btn: MouseButton::Left, x, y, .. } => PointerEvent::Down { pointer: mouse(which), x, y, timestamp, eraser: false, pressure: None },
+      Event::MouseButtonUp { timestamp, which, mouse_btn: MouseButton::Left, x, y, .. } => PointerEvent::Up { pointer: mouse(which), x, y, timestamp },
+      Event::MouseMotion { timestamp, which, x, y, .. } => PointerEvent::Motion { pointer: mouse(which), x, y, timestamp },
+      
+      Event::FingerDown { touch_id, .. } |
+      Event::FingerUp { touch_id, .. } |
+      Event::FingerMotion { touch_id, .. } if touch_id == MOUSE_TOUCH_ID || touch_id == PEN_TOUCH_ID => return None,
+      Event::FingerDown { timestamp, touch_id, finger_id, x, y, pressure, .. } => PointerEvent::Down { pointer: finger(touch_id, finger_id), x: x * width, y: y * height, timestamp, eraser: false, pressure: Some(pressure) },
+      Event::FingerUp { timestamp, touch_id, finger_id, x, y, .. } => PointerEvent::Up { pointer: finger(touch_id, finger_id), x: x * width, y: y * height, timestamp },
+      Event::FingerMotion { timestamp, touch_id, finger_id, x, y, .. } => PointerEvent::Motion { pointer: finger(touch_id, finger_id), x: x * width, y: y * height, timestamp },
+      _ => return None
+    };
+    
+    Some(translated)
+  }
+}
+
+// Which input sources are allowed to write and press buttons
+#[derive(Clone)]
+pub struct InputSettings {
+  pub pen: bool,
+  pub mouse: bool,
+  pub touch: bool
+}
+
+impl InputSettings {
+  pub fn new(config: &Config) -> Self {
+    if config.input.is_empty() {
+      log::warn!("No input sources are enabled in the config, nothing can write");
+    }
+    
+    Self {
+      pen: config.input.contains(&InputSource::Pen),
+      mouse: config.input.contains(&InputSource::Mouse),
+      touch: config.input.contains(&InputSource::Touch)
+    }
+  }
+  
+  pub fn accepts(&self, source: InputSource) -> bool {
+    match source {
+      InputSource::Pen => self.pen,
+      InputSource::Mouse => self.mouse,
+      InputSource::Touch => self.touch
+    }
+  }
+}
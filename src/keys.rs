@@ -0,0 +1,155 @@
+use rdev::Key;
+use sdl3::keyboard::{Keycode, Mod};
+
+// Names of keys and modifiers in the config, shared by the key chords
+// of buttons and by shortcuts so a name means the same in both
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Modifier {
+  Ctrl,
+  Shift,
+  Alt,
+  Super
+}
+
+const MODIFIERS: &[(&str, Modifier)] = &[
+  ("ctrl", Modifier::Ctrl),
+  ("control", Modifier::Ctrl),
+  ("shift", Modifier::Shift),
+  ("alt", Modifier::Alt),
+  ("super", Modifier::Super),
+  ("meta", Modifier::Super),
+  ("win", Modifier::Super)
+];
+
+// Key as typed by the simulator and as SDL reports it with no modifier held
+const KEYS: &[(&str, Key, Keycode)] = &[
+  ("tab", Key::Tab, Keycode::Tab),
+  ("enter", Key::Return, Keycode::Return),
+  ("return", Key::Return, Keycode::Return),
+  ("space", Key::Space, Keycode::Space),
+  ("backspace", Key::Backspace, Keycode::Backspace),
+  ("delete", Key::Delete, Keycode::Delete),
+  ("del", Key::Delete, Keycode::Delete),
+  ("insert", Key::Insert, Keycode::Insert),
+  ("escape", Key::Escape, Keycode::Escape),
+  ("esc", Key::Escape, Keycode::Escape),
+  ("up", Key::UpArrow, Keycode::Up),
+  ("down", Key::DownArrow, Keycode::Down),
+  ("left", Key::LeftArrow, Keycode::Left),
+  ("right", Key::RightArrow, Keycode::Right),
+  ("home", Key::Home, Keycode::Home),
+  ("end", Key::End, Keycode::End),
+  ("pageup", Key::PageUp, Keycode::PageUp),
+  ("pagedown", Key::PageDown, Keycode::PageDown),
+  ("f1", Key::F1, Keycode::F1),
+  ("f2", Key::F2, Keycode::F2),
+  ("f3", Key::F3, Keycode::F3),
+  ("f4", Key::F4, Keycode::F4),
+  ("f5", Key::F5, Keycode::F5),
+  ("f6", Key::F6, Keycode::F6),
+  ("f7", Key::F7, Keycode::F7),
+  ("f8", Key::F8, Keycode::F8),
+  ("f9", Key::F9, Keycode::F9),
+  ("f10", Key::F10, Keycode::F10),
+  ("f11", Key::F11, Keycode::F11),
+  ("f12", Key::F12, Keycode::F12),
+  ("0", Key::Num0, Keycode::_0),
+  ("1", Key::Num1, Keycode::_1),
+  ("2", Key::Num2, Keycode::_2),
+  ("3", Key::Num3, Keycode::_3),
+  ("4", Key::Num4, Keycode::_4),
+  ("5", Key::Num5, Keycode::_5),
+  ("6", Key::Num6, Keycode::_6),
+  ("7", Key::Num7, Keycode::_7),
+  ("8", Key::Num8, Keycode::_8),
+  ("9", Key::Num9, Keycode::_9),
+  ("a", Key::KeyA, Keycode::A),
+  ("b", Key::KeyB, Keycode::B),
+  ("c", Key::KeyC, Keycode::C),
+  ("d", Key::KeyD, Keycode::D),
+  ("e", Key::KeyE, Keycode::E),
+  ("f", Key::KeyF, Keycode::F),
+  ("g", Key::KeyG, Keycode::G),
+  ("h", Key::KeyH, Keycode::H),
+  ("i", Key::KeyI, Keycode::I),
+  ("j", Key::KeyJ, Keycode::J),
+  ("k", Key::KeyK, Keycode::K),
+  ("l", Key::KeyL, Keycode::L),
+  ("m", Key::KeyM, Keycode::M),
+  ("n", Key::KeyN, Keycode::N),
+  ("o", Key::KeyO, Keycode::O),
+  ("p", Key::KeyP, Keycode::P),
+  ("q", Key::KeyQ, Keycode::Q),
+  ("r", Key::KeyR, Keycode::R),
+  ("s", Key::KeyS, Keycode::S),
+  ("t", Key::KeyT, Keycode::T),
+  ("u", Key::KeyU, Keycode::U),
+  ("v", Key::KeyV, Keycode::V),
+  ("w", Key::KeyW, Keycode::W),
+  ("x", Key::KeyX, Keycode::X),
+  ("y", Key::KeyY, Keycode::Y),
+  ("z", Key::KeyZ, Keycode::Z)
+];
+
+impl Modifier {
+  pub const ALL: [Modifier; 4] = [Modifier::Ctrl, Modifier::Shift, Modifier::Alt, Modifier::Super];
+  
+  // Names are not case sensitive and may have spaces around them
+  pub fn parse(name: &str) -> Option<Self> {
+    let name = name.trim().to_ascii_lowercase();
+    MODIFIERS.iter()
+      .find(|(modifier_name, _)| *modifier_name == name)
+      .map(|(_, modifier)| *modifier)
+  }
+  
+  // Key pressed for it when typing
+  pub fn key(self) -> Key {
+    match self {
+      Modifier::Ctrl => Key::ControlLeft,
+      Modifier::Shift => Key::ShiftLeft,
+      Modifier::Alt => Key::Alt,
+      Modifier::Super => Key::MetaLeft
+    }
+  }
+  
+  // Either side held, as SDL reports it
+  pub fn keymod(self) -> Mod {
+    match self {
+      Modifier::Ctrl => Mod::LCTRLMOD | Mod::RCTRLMOD,
+      Modifier::Shift => Mod::LSHIFTMOD | Mod::RSHIFTMOD,
+      Modifier::Alt => Mod::LALTMOD | Mod::RALTMOD,
+      Modifier::Super => Mod::LGUIMOD | Mod::RGUIMOD
+    }
+  }
+}
+
+// Names are not case sensitive and may have spaces around them
+pub fn parse_key(name: &str) -> Option<(Key, Keycode)> {
+  let name = name.trim().to_ascii_lowercase();
+  KEYS.iter()
+    .find(|(key_name, _, _)| *key_name == name)
+    .map(|(_, key, keycode)| (*key, *keycode))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{action::KeyChord, shortcut::Shortcut};
+  
+  #[test]
+  fn chords_and_shortcuts_take_the_same_names() {
+    for (modifier, _) in MODIFIERS {
+      for (key, _, _) in KEYS {
+        let keys = format!("{modifier}+{key}");
+        assert!(KeyChord::try_from(keys.clone()).is_ok(), "chord '{keys}'");
+        assert!(Shortcut::try_from(keys.clone()).is_ok(), "shortcut '{keys}'");
+      }
+    }
+    
+    assert!(KeyChord::try_from("hyper+z".to_string()).is_err());
+    assert!(Shortcut::try_from("hyper+z".to_string()).is_err());
+    assert!(KeyChord::try_from("ctrl+plus".to_string()).is_err());
+    assert!(Shortcut::try_from("ctrl+plus".to_string()).is_err());
+  }
+}
@@ -1,13 +1,14 @@
 #![feature(thread_sleep_until)]
 
-use std::{io::stdout, sync::{Arc, Condvar, Mutex, atomic::Ordering}, thread, time::Duration};
+use std::{io::stdout, sync::{Arc, Condvar, Mutex, atomic::Ordering}, thread, time::{Duration, Instant}};
 
 use log::LevelFilter;
 use sdl3::{event::{Event, WindowEvent}, keyboard::Keycode, pixels::{Color, PixelFormat}};
-use taffy::{AvailableSpace, FlexDirection, FlexWrap, Size, Style, TaffyTree, prelude::FromLength};
+use taffy::{Dimension, FlexDirection, FlexWrap, Size, Style, prelude::FromLength};
 
-use crate::{button::Button, pixel_buffer::PixelBuffer, processing_thread::CURRENTLY_RECOGNIZED, shapes::Rect, timer::Timer, window::Window, writing_canvas::WritingCanvas};
+use crate::{action::Action, auto_submit::AutoSubmit, button::Button, candidates::Dictionary, config::Config, gesture::EditGesture, input::{InputSettings, InputSource, PointerEvent, PointerId}, palm_rejection::PalmRejection, pixel_buffer::PixelBuffer, preview::{Preview, PreviewState}, processing_thread::{CANDIDATES_REQUESTED, CURRENTLY_RECOGNIZED, CURRENT_BACKEND, FAILED_BACKEND, PROCESSING_ERROR, REQUESTED_BACKEND}, processor::BACKENDS, timer::Timer, widget::WidgetTree, window::Window, writing_canvas::WritingCanvas};
 
+mod action;
 mod timer;
 mod shapes;
 mod global;
@@ -19,6 +20,20 @@ mod button;
 mod simulator;
 mod window;
 mod pixel_buffer;
+mod input;
+mod palm_rejection;
+mod ink;
+mod gesture;
+mod auto_submit;
+mod candidates;
+mod font;
+mod icon;
+mod preview;
+mod config;
+mod data_dir;
+mod widget;
+mod shortcut;
+mod keys;
 
 fn init_sdl() -> Result<(), ()> {
   global::SDL.set(Some(
@@ -47,6 +62,9 @@ fn init_sdl() -> Result<(), ()> {
   Ok(())
 }
 
+// How long submit waits for the latest writing to be recognized
+const SUBMIT_TIMEOUT: Duration = Duration::from_secs(5);
+
 // .0 => the pixel buffer
 // .1 => number of updates to the writing canvas
 static CURRENT_PIXELS: Mutex<(Option<Arc<PixelBuffer>>, u64)> = Mutex::new((None, 0));
@@ -67,6 +85,7 @@ pub fn get_pixels() -> Option<(Arc<PixelBuffer>, u64)> {
 fn main() -> Result<(), ()> {
   simple_logging::log_to(stdout(), LevelFilter::max());
   sdl_log::init();
+  let config = Config::load();
   init_sdl()?;
   
   let mut event_pump = global::get_sdl().event_pump()
@@ -91,216 +110,142 @@ fn main() -> Result<(), ()> {
   log::info!("Everything is initialized");
   println!("Hello, world!");
   
+  let input_settings = InputSettings::new(&config);
+  let mut palm_rejection = PalmRejection::new(&config);
+  let mut auto_submit = AutoSubmit::new(&config);
   let mut timer = Timer::new(Duration::from_millis(1000 / 60));
   window.set_canvas_size(800, 300);
   
-  let mut clear_button = Button::new(Rect {
-    x1: (window.get_canvas_width() - 100) as f32,
-    y1: 20.0,
-    x2: (window.get_canvas_width() - 20) as f32,
-    y2: 80.0
-  }, window.get_canvas().clone());
-  
-  let mut submit_button = Button::new(Rect {
-    x1: (window.get_canvas_width() - 100) as f32,
-    y1: 20.0,
-    x2: (window.get_canvas_width() - 20) as f32,
-    y2: 80.0
-  }, window.get_canvas().clone());
-  
-  let mut enter_button = Button::new(Rect {
-    x1: (window.get_canvas_width() - 100) as f32,
-    y1: 20.0,
-    x2: (window.get_canvas_width() - 20) as f32,
-    y2: 80.0
-  }, window.get_canvas().clone());
+  let mut widgets = WidgetTree::new(Style {
+    padding: taffy::Rect::length(10.0),
+    gap: Size::from_length(10.0),
+    size: Size::from_percent(1.0, 1.0),
+    flex_direction: FlexDirection::Column,
+    ..Default::default()
+  });
   
-  let mut space_button = Button::new(Rect {
-    x1: (window.get_canvas_width() - 100) as f32,
-    y1: 20.0,
-    x2: (window.get_canvas_width() - 20) as f32,
-    y2: 80.0
-  }, window.get_canvas().clone());
-  
-  let mut delword_button = Button::new(Rect {
-    x1: (window.get_canvas_width() - 100) as f32,
-    y1: 20.0,
-    x2: (window.get_canvas_width() - 20) as f32,
-    y2: 80.0
-  }, window.get_canvas().clone());
+  let preview = widgets.add(widgets.root(), Style {
+    size: Size {
+      width: Dimension::auto(),
+      height: Dimension::length(56.0)
+    },
+    ..Default::default()
+  }, Preview::new(window.get_canvas().clone()));
   
-  let mut tree = TaffyTree::<()>::new();
-  let writing_canvas_layout = tree.new_leaf(Style {
-      min_size: Size::from_lengths(100.0, 100.0),
+  // Row of corrections above the canvas and buttons
+  let candidate_bar = widgets.add_container(widgets.root(), Style {
+    gap: Size::from_length(10.0),
+    flex_direction: FlexDirection::Row,
+    ..Default::default()
+  });
+  let candidate_buttons: Vec<_> = (0..config.candidate_count)
+    .map(|_| widgets.add(candidate_bar, Style {
+      size: Size::from_lengths(0.0, 40.0),
       flex_grow: 1.0,
       ..Default::default()
-    }).unwrap();
-  
-  let clear_button_layout = tree.new_leaf(Style {
-      size: Size::from_lengths(100.0, 60.0),
-      ..Default::default()
-    }).unwrap();
-  
-  let submit_button_layout = tree.new_leaf(Style {
-      size: Size::from_lengths(100.0, 60.0),
-      ..Default::default()
-    }).unwrap();
+    }, Button::new(window.get_canvas().clone())))
+    .collect();
   
-  let enter_button_layout = tree.new_leaf(Style {
-      size: Size::from_lengths(100.0, 60.0),
-      ..Default::default()
-    }).unwrap();
+  let content = widgets.add_container(widgets.root(), Style {
+    gap: Size::from_length(10.0),
+    flex_grow: 1.0,
+    flex_direction: FlexDirection::Row,
+    flex_wrap: FlexWrap::Wrap,
+    ..Default::default()
+  });
   
-  let space_button_layout = tree.new_leaf(Style {
-      size: Size::from_lengths(100.0, 60.0),
-      ..Default::default()
-    }).unwrap();
+  let writing_canvas_widget = widgets.add(content, Style {
+    min_size: Size::from_lengths(100.0, 100.0),
+    flex_grow: 1.0,
+    ..Default::default()
+  }, WritingCanvas::new(window.get_canvas().clone())
+    .with_erase_whole_strokes(config.erase_whole_strokes)
+    .with_flick_gestures(config.flick_gestures));
   
-  let delword_button_layout = tree.new_leaf(Style {
-      size: Size::from_lengths(100.0, 60.0),
-      ..Default::default()
-    }).unwrap();
-  
-  let buttons_layout = tree.new_with_children(
-    Style {
-      gap: Size::from_length(10.0),
-      flex_direction: FlexDirection::Column,
-      flex_wrap: FlexWrap::Wrap,
-      ..Default::default()
-    },
-    &[
-      clear_button_layout,
-      submit_button_layout,
-      enter_button_layout,
-      space_button_layout,
-      delword_button_layout
-    ]
-  ).unwrap();
-  
-  let root = tree.new_with_children(
-    Style {
-      padding: taffy::Rect::length(10.0),
-      gap: Size::from_length(10.0),
-      size: Size::from_percent(1.0, 1.0),
-      flex_direction: FlexDirection::Row,
-      flex_wrap: FlexWrap::Wrap,
-      ..Default::default()
-    },
-    &[
-      writing_canvas_layout,
-      buttons_layout
-    ]
-  ).unwrap();
-  
-  let mut writing_canvas = WritingCanvas::new(Rect {
-      x1: 20.0,
-      y1: 20.0, 
-      x2: (window.get_canvas_width() - 120) as f32,
-      y2: (window.get_canvas_height() - 20) as f32
-    }, window.get_canvas().clone());
-  
-  let mut recompute_layout = |writing_canvas: &mut WritingCanvas, clear_button: &mut Button, submit_button: &mut Button, enter_button: &mut Button, delword_button: &mut Button, space_button: &mut Button| -> () {
-    tree.compute_layout(
-      root,
-      Size {
-        width: AvailableSpace::Definite(window.get_width() as f32),
-        height: AvailableSpace::Definite(window.get_height() as f32)
+  let buttons = widgets.add_container(content, Style {
+    gap: Size::from_length(10.0),
+    flex_direction: FlexDirection::Column,
+    flex_wrap: FlexWrap::Wrap,
+    ..Default::default()
+  });
+  // Buttons of the configured bar, each kept with what it does
+  let bar_buttons: Vec<_> = config.button_bar().iter()
+    .map(|button_config| {
+      let mut button = Button::new(window.get_canvas().clone()).with_label(&button_config.label);
+      if let Some(icon) = button_config.action.icon() {
+        button = button.with_icon(icon);
       }
-    ).unwrap();
-    
+      
+      (button_config.clone(), widgets.add(buttons, Style {
+        size: Size::from_lengths(100.0, 60.0),
+        ..Default::default()
+      }, button))
+    })
+    .collect();
+  
+  let recompute_layout = |widgets: &mut WidgetTree| {
+    widgets.compute_layout(window.get_width() as f32, window.get_height() as f32);
     window.set_canvas_size(window.get_width(), window.get_height());
-    
-    let root = tree.layout(root).unwrap();
-    let root_x = root.location.x;
-    let root_y = root.location.y;
-    let new_layout = tree.layout(writing_canvas_layout).unwrap();
-    writing_canvas.set_bound(Rect {
-      x1: root_x + new_layout.content_box_x(),
-      y1: root_y + new_layout.content_box_y(),
-      x2: root_x + new_layout.content_box_x() + new_layout.content_box_width(),
-      y2: root_y + new_layout.content_box_y() + new_layout.content_box_height()
-    });
-    
-    let new_layout = tree.layout(clear_button_layout).unwrap();
-    let parent = tree.layout(buttons_layout).unwrap();
-    let parent_x = root_x + parent.location.x;
-    let parent_y = root_y + parent.location.y;
-    clear_button.set_bound(Rect {
-      x1: parent_x + new_layout.content_box_x(),
-      y1: parent_y + new_layout.content_box_y(),
-      x2: parent_x + new_layout.content_box_x() + new_layout.content_box_width(),
-      y2: parent_y + new_layout.content_box_y() + new_layout.content_box_height()
-    });
-    
-    let new_layout = tree.layout(submit_button_layout).unwrap();
-    submit_button.set_bound(Rect {
-      x1: parent_x + new_layout.content_box_x(),
-      y1: parent_y + new_layout.content_box_y(),
-      x2: parent_x + new_layout.content_box_x() + new_layout.content_box_width(),
-      y2: parent_y + new_layout.content_box_y() + new_layout.content_box_height()
-    });
-    
-    let new_layout = tree.layout(enter_button_layout).unwrap();
-    enter_button.set_bound(Rect {
-      x1: parent_x + new_layout.content_box_x(),
-      y1: parent_y + new_layout.content_box_y(),
-      x2: parent_x + new_layout.content_box_x() + new_layout.content_box_width(),
-      y2: parent_y + new_layout.content_box_y() + new_layout.content_box_height()
-    });
-    
-    let new_layout = tree.layout(delword_button_layout).unwrap();
-    delword_button.set_bound(Rect {
-      x1: parent_x + new_layout.content_box_x(),
-      y1: parent_y + new_layout.content_box_y(),
-      x2: parent_x + new_layout.content_box_x() + new_layout.content_box_width(),
-      y2: parent_y + new_layout.content_box_y() + new_layout.content_box_height()
-    });
-    
-    let new_layout = tree.layout(space_button_layout).unwrap();
-    space_button.set_bound(Rect {
-      x1: parent_x + new_layout.content_box_x(),
-      y1: parent_y + new_layout.content_box_y(),
-      x2: parent_x + new_layout.content_box_x() + new_layout.content_box_width(),
-      y2: parent_y + new_layout.content_box_y() + new_layout.content_box_height()
-    });
   };
   
-  recompute_layout(&mut writing_canvas, &mut clear_button, &mut submit_button, &mut enter_button, &mut delword_button, &mut space_button);
+  recompute_layout(&mut widgets);
   
-  let processing_thread_handle = thread::spawn(processing_thread::main);
+  let dictionary = Dictionary::load(&config);
+  let processing_config = config.clone();
+  let processing_thread_handle = thread::spawn(move || processing_thread::main(processing_config, dictionary));
   let simulator_thread_handle = thread::spawn(simulator::main);
   
+  // Update count of the writing canvas last sent to processing thread
+  let mut sent_update_count = writing_canvas_widget.borrow().get_update_count();
+  // Submit waiting for the recognition to catch up
+  let mut submit_requested_at: Option<Instant> = None;
+  
   'main_loop: loop {
-    let old_count = writing_canvas.get_update_count();
-    
-    clear_button.reset();
-    submit_button.reset();
-    enter_button.reset();
-    space_button.reset();
-    delword_button.reset();
+    widgets.begin_frame();
+    // What buttons, shortcuts and gestures asked for this frame
+    let mut actions: Vec<Action> = Vec::new();
     
     for event in event_pump.poll_iter() {
+      if let Some(pointer_event) = PointerEvent::from_sdl(&event, &window) {
+        if !input_settings.accepts(pointer_event.pointer().source) {
+          continue;
+        }
+        
+        let is_accepted = palm_rejection.filter(&pointer_event);
+        for pointer in palm_rejection.take_cancelled() {
+          log::info!("Cancelling touch {pointer:?}, looks like a palm");
+          widgets.cancel(pointer);
+        }
+        
+        if !is_accepted {
+          continue;
+        }
+        
+        if let PointerEvent::Down { .. } = pointer_event {
+          auto_submit.pen_down();
+        }
+        widgets.pointer_event(&pointer_event);
+        continue;
+      }
+      
       match event {
-        Event::PenDown { x, y, which, .. } => {
-          writing_canvas.pen_down(x, y, which);
-          clear_button.pen_down(x, y);
-          submit_button.pen_down(x, y);
-          enter_button.pen_down(x, y);
-          space_button.pen_down(x, y);
-          delword_button.pen_down(x, y);
+        Event::PenProximityIn { timestamp, which, .. } => {
+          palm_rejection.pen_proximity(which, true, timestamp);
         }
-        Event::PenUp { x, y, which, .. } => {
-          writing_canvas.pen_up(x, y, which);
-          clear_button.pen_up(x, y);
-          submit_button.pen_up(x, y);
-          enter_button.pen_up(x, y);
-          space_button.pen_up(x, y);
-          delword_button.pen_up(x, y);
+        Event::PenProximityOut { timestamp, which, .. } => {
+          palm_rejection.pen_proximity(which, false, timestamp);
         }
-        Event::PenMotion { x, y, which, .. } => {
-          writing_canvas.pen_motion(x, y, which);
+        Event::PenAxis { which, axis, value, .. } if input_settings.accepts(InputSource::Pen) => {
+          writing_canvas_widget.borrow_mut().pen_axis(PointerId::pen(which), axis, value);
         }
         Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'main_loop,
+        Event::KeyDown { scancode: Some(scancode), keymod, repeat: false, .. } => {
+          actions.extend(
+            config.shortcuts.iter()
+              .filter(|shortcut| shortcut.keys.matches(scancode, keymod))
+              .map(|shortcut| shortcut.action.clone())
+          );
+        }
         Event::Quit { .. } => break 'main_loop,
         Event::Window { window_id, win_event: WindowEvent::Resized(_, _), .. } => {
           if window_id != window.get_window_id() {
@@ -308,57 +253,202 @@ fn main() -> Result<(), ()> {
             continue;
           }
           
-          recompute_layout(&mut writing_canvas, &mut clear_button, &mut submit_button, &mut enter_button, &mut delword_button, &mut space_button);
+          recompute_layout(&mut widgets);
         }
         _ => ()
       }
     }
     
-    if clear_button.is_pressed() {
-      log::info!("Clearing writing canvas");
-      writing_canvas.clear();
-      *CURRENTLY_RECOGNIZED.lock().unwrap() = None;
+    let mut canvas_borrow = window.get_canvas().borrow_mut();
+    canvas_borrow.set_draw_color(Color::RGB(0x55, 0x55, 0x55));
+    canvas_borrow.clear();
+    drop(canvas_borrow);
+    
+    let mut writing_canvas = writing_canvas_widget.borrow_mut();
+    
+    actions.extend(
+      bar_buttons.iter()
+        .filter(|(_, button)| button.borrow().is_pressed())
+        .map(|(button_config, _)| button_config.action.clone())
+    );
+    
+    match writing_canvas.take_gesture() {
+      Some(EditGesture::Space) => actions.push(Action::Space),
+      Some(EditGesture::DelWord) => actions.push(Action::DelWord),
+      Some(EditGesture::Enter) => actions.push(Action::Enter),
+      None => ()
+    }
+    
+    if writing_canvas.take_finished_stroke() {
+      auto_submit.stroke_finished();
     }
     
-    if submit_button.is_pressed() {
-      if let Some(text) = CURRENTLY_RECOGNIZED.lock().unwrap().clone() {
-        log::info!("Submitting: {text}");
-        simulator::simulate(text);
-        simulator_thread_handle.thread().unpark();
-        writing_canvas.clear();
-      } else {
-        log::info!("No text is recognized yet, please write");
+    if writing_canvas.is_empty() {
+      auto_submit.cancel();
+    }
+    
+    if auto_submit.is_due() {
+      actions.push(Action::Submit);
+    }
+    
+    for action in actions {
+      match action {
+        Action::Clear => {
+          log::info!("Clearing writing canvas");
+          writing_canvas.clear();
+          *CURRENTLY_RECOGNIZED.lock().unwrap() = None;
+        }
+        Action::Submit => {
+          auto_submit.cancel();
+          submit_requested_at.get_or_insert_with(Instant::now);
+        }
+        Action::Undo => {
+          if !writing_canvas.undo() {
+            log::info!("Nothing to undo");
+          }
+        }
+        Action::Redo => {
+          if !writing_canvas.redo() {
+            log::info!("Nothing to redo");
+          }
+        }
+        Action::Eraser => writing_canvas.toggle_eraser(),
+        // Cycles through the backends so they can be compared
+        Action::Backend => {
+          let current = *CURRENT_BACKEND.lock().unwrap();
+          let index = BACKENDS.iter()
+            .position(|backend| Some(backend.name) == current)
+            .map_or(0, |index| (index + 1) % BACKENDS.len());
+          log::info!("Switching recognition to {}", BACKENDS[index].name);
+          *FAILED_BACKEND.lock().unwrap() = None;
+          *REQUESTED_BACKEND.lock().unwrap() = Some(BACKENDS[index].name);
+          processing_thread_handle.thread().unpark();
+        }
+        Action::Enter => {
+          simulator::simulate_enter();
+          simulator_thread_handle.thread().unpark();
+        }
+        Action::Space => {
+          simulator::simulate_space();
+          simulator_thread_handle.thread().unpark();
+        }
+        Action::DelWord => {
+          simulator::simulate_delword();
+          simulator_thread_handle.thread().unpark();
+        }
+        Action::Keys(chord) => {
+          simulator::simulate_keys(chord.0);
+          simulator_thread_handle.thread().unpark();
+        }
+        Action::Text(text) => {
+          simulator::simulate(text);
+          simulator_thread_handle.thread().unpark();
+        }
       }
     }
     
-    if enter_button.is_pressed() {
-      simulator::simulate_enter();
-      simulator_thread_handle.thread().unpark();
+    // Submit waits until the latest writing is recognized, so
+    // the last letters are not missing from the typed text
+    let processing_error = PROCESSING_ERROR.lock().unwrap().clone();
+    // Whether recognition of the given canvas update failed
+    let has_failed = |update_id: u64| processing_error.as_ref()
+      .is_some_and(|error| error.update_id.is_none_or(|id| id == update_id));
+    if let Some(requested_at) = submit_requested_at {
+      let recognized = CURRENTLY_RECOGNIZED.lock().unwrap().clone();
+      match recognized {
+        _ if writing_canvas.is_empty() => {
+          log::info!("No text is recognized yet, please write");
+          submit_requested_at = None;
+        }
+        Some(recognized) if recognized.update_id == writing_canvas.get_update_count() => {
+          log::info!("Submitting: {}", recognized.text);
+          simulator::simulate(recognized.text);
+          simulator_thread_handle.thread().unpark();
+          writing_canvas.reset();
+          *CURRENTLY_RECOGNIZED.lock().unwrap() = None;
+          submit_requested_at = None;
+        }
+        _ if has_failed(writing_canvas.get_update_count()) => {
+          log::warn!("Recognition failed, not submitting: {}", processing_error.as_ref().unwrap().message);
+          submit_requested_at = None;
+        }
+        _ if requested_at.elapsed() >= SUBMIT_TIMEOUT => {
+          log::warn!("Recognition did not catch up in {SUBMIT_TIMEOUT:?}, not submitting");
+          submit_requested_at = None;
+        }
+        _ => ()
+      }
     }
     
-    if delword_button.is_pressed() {
-      simulator::simulate_delword();
-      simulator_thread_handle.thread().unpark();
+    // Candidate replaces the word it is for, then the processing thread
+    // finds candidates for the next least confident word
+    for (index, candidate_button) in candidate_buttons.iter().enumerate() {
+      if !candidate_button.borrow().is_pressed() {
+        continue;
+      }
+      
+      let mut recognized = CURRENTLY_RECOGNIZED.lock().unwrap();
+      let Some(recognized) = recognized.as_mut().filter(|recognized| recognized.update_id == writing_canvas.get_update_count()) else {
+        continue;
+      };
+      let Some(candidates) = recognized.candidates.take() else {
+        continue;
+      };
+      
+      if let Some(text) = candidates.texts.get(index) {
+        log::info!("Replacing '{}' with '{text}'", recognized.recognition.regions[candidates.region].text);
+        Arc::make_mut(&mut recognized.recognition).replace_region(candidates.region, text.clone());
+        recognized.text = recognized.recognition.text.clone();
+        CANDIDATES_REQUESTED.store(true, Ordering::Relaxed);
+        processing_thread_handle.thread().unpark();
+      } else {
+        recognized.candidates = Some(candidates);
+      }
     }
     
-    if space_button.is_pressed() {
-      simulator::simulate_space();
-      simulator_thread_handle.thread().unpark();
+    // Backend buttons show which backend is in use, or
+    // which one could not be switched to until it is pressed again
+    let current_backend = *CURRENT_BACKEND.lock().unwrap();
+    let failed_backend = *FAILED_BACKEND.lock().unwrap();
+    for (button_config, button) in bar_buttons.iter() {
+      match button_config.action {
+        Action::Eraser => button.borrow_mut().set_toggled(writing_canvas.is_eraser_toggled()),
+        Action::Backend => match failed_backend {
+          Some(failed) => button.borrow_mut().set_label(&format!("{failed} failed")),
+          None => button.borrow_mut().set_label(current_backend.unwrap_or(&button_config.label))
+        },
+        _ => ()
+      }
     }
     
-    let mut canvas_borrow = window.get_canvas().borrow_mut();
-    canvas_borrow.set_draw_color(Color::RGB(0x55, 0x55, 0x55));
-    canvas_borrow.clear();
-    drop(canvas_borrow);
+    let recognized = CURRENTLY_RECOGNIZED.lock().unwrap().clone();
+    // Recognition and candidates only matter while they match what is on the canvas
+    let fresh = recognized.as_ref()
+      .filter(|recognized| recognized.update_id == writing_canvas.get_update_count());
+    let preview_state = match fresh {
+      Some(_) => PreviewState::Fresh,
+      None if has_failed(writing_canvas.get_update_count()) => PreviewState::Stale,
+      None => PreviewState::Pending
+    };
+    preview.borrow_mut().show(recognized.as_ref().map(|recognized| recognized.recognition.clone()), preview_state);
+    
+    // Only slots with a candidate are shown
+    let candidate_texts = fresh
+      .and_then(|recognized| recognized.candidates.as_ref())
+      .map_or(&[][..], |candidates| &candidates.texts);
+    for (index, candidate_button) in candidate_buttons.iter().enumerate() {
+      let mut candidate_button = candidate_button.borrow_mut();
+      candidate_button.set_visible(index < candidate_texts.len());
+      if let Some(text) = candidate_texts.get(index) {
+        candidate_button.set_label(text);
+      }
+    }
     
-    writing_canvas.draw();
-    clear_button.draw();
-    submit_button.draw();
-    enter_button.draw();
-    delword_button.draw();
-    space_button.draw();
+    drop(writing_canvas);
+    widgets.draw();
+    let writing_canvas = writing_canvas_widget.borrow();
     
-    if writing_canvas.get_update_count() > old_count {
+    if writing_canvas.get_update_count() != sent_update_count {
       if let Ok(mut current_pixels) = CURRENT_PIXELS.try_lock() {
         writing_canvas.with_pixels(|bytes, width, height, pitch, pixel_format| {
           let pitch = usize::try_from(pitch).unwrap();
@@ -386,12 +476,32 @@ fn main() -> Result<(), ()> {
         });
         
         current_pixels.1 = writing_canvas.get_update_count();
+        sent_update_count = current_pixels.1;
         
         processing_thread_handle.thread().unpark();
         CURRENT_PIXELS_COND.notify_all();
       }
     }
     
+    if let Some(remaining) = auto_submit.remaining() {
+      writing_canvas.draw_countdown(remaining);
+    }
+    
+    if let Some(requested_at) = submit_requested_at {
+      writing_canvas.draw_busy(requested_at.elapsed());
+    }
+    
+    if processing_error.is_some() {
+      writing_canvas.draw_error();
+    }
+    
+    if let Some(recognized) = fresh {
+      writing_canvas.draw_low_confidence(
+        recognized.recognition.regions.iter()
+          .filter(|region| region.is_low_confidence)
+          .map(|region| &region.bounding_box)
+      );
+    }
     window.get_canvas().borrow_mut().present();
     timer.wait_tick(1);
   }
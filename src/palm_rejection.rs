@@ -0,0 +1,196 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{config::Config, input::{InputSource, PointerEvent, PointerId}, shapes::Point};
+
+// Drops touch contacts which are likely the hand resting on the
+// screen while writing with the stylus
+pub struct PalmRejection {
+  // Pens currently near or touching the screen
+  pens_nearby: HashSet<u32>,
+  // Pens which report proximity, others only count while touching
+  pens_with_proximity: HashSet<u32>,
+  // Timestamp (ns) of when the last pen went away
+  last_pen_away: Option<u64>,
+  // Touches being tracked and where they went down
+  active_touches: HashMap<PointerId, Point>,
+  rejected_touches: HashSet<PointerId>,
+  // Touches which were accepted but rejected later, the
+  // receivers should throw away what these did
+  cancelled: Vec<PointerId>,
+  // How long after pen went away touches are still ignored
+  grace_period_ns: u64,
+  // Touch pressure at and above this is treated as a large contact,
+  // None to not look at pressure
+  large_contact_pressure: Option<f32>,
+  // First pressure reported by each touch device, many devices
+  // (and SDL on X11 and Wayland) always report the same value
+  first_pressure: HashMap<u64, f32>,
+  // Touch devices which reported more than one pressure, only
+  // their pressure tells anything about the contact
+  varying_pressure: HashSet<u64>,
+  // Touches going down this close to another one are treated as one large contact
+  large_contact_radius: f32
+}
+
+impl PalmRejection {
+  pub fn new(config: &Config) -> Self {
+    Self {
+      pens_nearby: HashSet::new(),
+      pens_with_proximity: HashSet::new(),
+      last_pen_away: None,
+      active_touches: HashMap::new(),
+      rejected_touches: HashSet::new(),
+      cancelled: Vec::new(),
+      grace_period_ns: 500_000_000,
+      large_contact_pressure: config.palm_pressure,
+      first_pressure: HashMap::new(),
+      varying_pressure: HashSet::new(),
+      large_contact_radius: 60.0
+    }
+  }
+  
+  pub fn pen_proximity(&mut self, pen: u32, is_near: bool, timestamp: u64) {
+    self.pens_with_proximity.insert(pen);
+    if is_near {
+      self.pen_near(pen);
+    } else {
+      self.pen_away(pen, timestamp);
+    }
+  }
+  
+  fn pen_near(&mut self, pen: u32) {
+    self.pens_nearby.insert(pen);
+    // Palm is usually already resting when pen comes close
+    self.reject_active_touches();
+  }
+  
+  fn pen_away(&mut self, pen: u32, timestamp: u64) {
+    if self.pens_nearby.remove(&pen) && self.pens_nearby.is_empty() {
+      self.last_pen_away = Some(timestamp);
+    }
+  }
+  
+  // Whether the event should be passed on
+  pub fn filter(&mut self, event: &PointerEvent) -> bool {
+    let pointer = event.pointer();
+    if pointer.source == InputSource::Pen {
+      // Not every pen reports proximity, the contact counts as nearby too
+      let pen = pointer.id as u32;
+      match event {
+        PointerEvent::Down { .. } => self.pen_near(pen),
+        PointerEvent::Up { timestamp, .. } if !self.pens_with_proximity.contains(&pen) => self.pen_away(pen, *timestamp),
+        _ => ()
+      }
+      return true;
+    }
+    
+    if pointer.source != InputSource::Touch {
+      return true;
+    }
+    
+    match event {
+      PointerEvent::Down { pressure, timestamp, .. } => {
+        let position = event.position();
+        let is_large = pressure.is_some_and(|pressure| self.is_large_pressure(pointer.device, pressure)) ||
+          self.active_touches.values().any(|other| other.distance(&position) <= self.large_contact_radius);
+        
+        if is_large {
+          // Everything from the same hand goes too
+          self.reject_active_touches();
+        }
+        
+        if is_large || self.is_pen_active(*timestamp) {
+          self.rejected_touches.insert(pointer);
+          return false;
+        }
+        
+        self.active_touches.insert(pointer, position);
+        true
+      }
+      PointerEvent::Motion { .. } => !self.rejected_touches.contains(&pointer),
+      PointerEvent::Up { .. } => {
+        self.active_touches.remove(&pointer);
+        !self.rejected_touches.remove(&pointer)
+      }
+    }
+  }
+  
+  // Touches accepted earlier which turned out to be palm
+  pub fn take_cancelled(&mut self) -> Vec<PointerId> {
+    std::mem::take(&mut self.cancelled)
+  }
+  
+  fn is_large_pressure(&mut self, device: u64, pressure: f32) -> bool {
+    let Some(threshold) = self.large_contact_pressure else {
+      return false;
+    };
+    
+    let first = *self.first_pressure.entry(device).or_insert(pressure);
+    if (pressure - first).abs() > f32::EPSILON {
+      self.varying_pressure.insert(device);
+    }
+    
+    self.varying_pressure.contains(&device) && pressure >= threshold
+  }
+  
+  fn is_pen_active(&self, timestamp: u64) -> bool {
+    if !self.pens_nearby.is_empty() {
+      return true;
+    }
+    
+    self.last_pen_away.is_some_and(|away| timestamp.saturating_sub(away) < self.grace_period_ns)
+  }
+  
+  fn reject_active_touches(&mut self) {
+    for (pointer, _) in self.active_touches.drain() {
+      self.rejected_touches.insert(pointer);
+      self.cancelled.push(pointer);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  fn finger_down(id: u64, x: f32, pressure: f32) -> PointerEvent {
+    PointerEvent::Down {
+      pointer: PointerId { source: InputSource::Touch, device: 1, id },
+      x,
+      y: 0.0,
+      timestamp: 0,
+      eraser: false,
+      pressure: Some(pressure)
+    }
+  }
+  
+  fn palm_rejection(palm_pressure: Option<f32>) -> PalmRejection {
+    PalmRejection::new(&Config {
+      palm_pressure,
+      ..Config::default()
+    })
+  }
+  
+  #[test]
+  fn constant_pressure_is_not_a_palm() {
+    let mut palm_rejection = palm_rejection(Some(0.9));
+    assert!(palm_rejection.filter(&finger_down(1, 0.0, 1.0)));
+    assert!(palm_rejection.filter(&finger_down(2, 500.0, 1.0)));
+    assert!(palm_rejection.take_cancelled().is_empty());
+  }
+  
+  #[test]
+  fn varying_pressure_over_threshold_is_a_palm() {
+    let mut palm_rejection = palm_rejection(Some(0.9));
+    assert!(palm_rejection.filter(&finger_down(1, 0.0, 0.3)));
+    assert!(!palm_rejection.filter(&finger_down(2, 500.0, 0.95)));
+    assert_eq!(palm_rejection.take_cancelled().len(), 1);
+  }
+  
+  #[test]
+  fn pressure_is_ignored_by_default() {
+    let mut palm_rejection = palm_rejection(None);
+    assert!(palm_rejection.filter(&finger_down(1, 0.0, 0.3)));
+    assert!(palm_rejection.filter(&finger_down(2, 500.0, 0.95)));
+  }
+}
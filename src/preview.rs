@@ -0,0 +1,123 @@
+use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+use sdl3::{pixels::Color, render::Canvas, video::Window};
+
+use crate::{font, processor::Recognition, shapes::Rect, widget::Widget};
+
+const TEXT_SCALE: f32 = 1.5;
+const PADDING: f32 = 4.0;
+// Colored strip on the left telling the state
+const STATE_WIDTH: f32 = 6.0;
+
+// How the shown text relates to the writing on the canvas
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PreviewState {
+  // Recognized from exactly what is on the canvas
+  Fresh,
+  // Canvas changed since and is being recognized again
+  Pending,
+  // Canvas changed since and recognition failed, the text will not catch up
+  Stale
+}
+
+// Shows the text which would be typed on submit
+pub struct Preview {
+  canvas: Rc<RefCell<Canvas<Window>>>,
+  bound: Rect,
+  recognition: Option<Arc<Recognition>>,
+  state: PreviewState
+}
+
+impl Preview {
+  pub fn new(canvas: Rc<RefCell<Canvas<Window>>>) -> Self {
+    Self {
+      canvas,
+      bound: Rect::default(),
+      recognition: None,
+      state: PreviewState::Pending
+    }
+  }
+  
+  pub fn show(&mut self, recognition: Option<Arc<Recognition>>, state: PreviewState) {
+    self.recognition = recognition;
+    self.state = state;
+  }
+}
+
+impl Widget for Preview {
+  fn set_bound(&mut self, bound: Rect) {
+    self.bound = bound;
+  }
+  
+  fn draw(&self) {
+    let mut canvas = self.canvas.borrow_mut();
+    let (background, strip, text_color) = match self.state {
+      PreviewState::Fresh => (Color::RGB(0xEE, 0xFF, 0xEE), Color::RGB(0x33, 0xAA, 0x55), Color::BLACK),
+      PreviewState::Pending => (Color::RGB(0xFF, 0xF6, 0xDD), Color::RGB(0xCC, 0x88, 0x33), Color::BLACK),
+      PreviewState::Stale => (Color::RGB(0xDD, 0xDD, 0xDD), Color::RGB(0x88, 0x88, 0x88), Color::RGB(0x77, 0x77, 0x77))
+    };
+    
+    canvas.set_draw_color(background);
+    let _ = canvas.fill_rect(Some(self.bound.clone().into()))
+      .map_err(|e| log::warn!("error calling canvas.fill_rect {e}"));
+    
+    let strip_bound = Rect {
+      x2: self.bound.x1 + STATE_WIDTH,
+      ..self.bound.clone()
+    };
+    canvas.set_draw_color(strip);
+    let _ = canvas.fill_rect(Some(strip_bound.into()))
+      .map_err(|e| log::warn!("error calling canvas.fill_rect {e}"));
+    
+    let Some(recognition) = &self.recognition else {
+      return;
+    };
+    
+    // Words of each line with whether they are low confidence
+    let mut lines: Vec<Vec<(&str, bool)>> = Vec::new();
+    for region in recognition.regions.iter() {
+      if lines.len() <= region.line {
+        lines.resize_with(region.line + 1, Vec::new);
+      }
+      lines[region.line].push((&region.text, region.is_low_confidence));
+    }
+    lines.retain(|line| !line.is_empty());
+    
+    let text_x = self.bound.x1 + STATE_WIDTH + PADDING;
+    let text_width = self.bound.x2 - PADDING - text_x;
+    let line_height = font::line_height(TEXT_SCALE);
+    let char_width = font::GLYPH_WIDTH * TEXT_SCALE;
+    // Latest writing is the most interesting, when the text does
+    // not fit the last lines and the end of lines are shown
+    let visible_lines = (((self.bound.y2 - self.bound.y1 - PADDING * 2.0) / line_height).floor() as usize).max(1);
+    let first_line = lines.len().saturating_sub(visible_lines);
+    
+    let clip: sdl3::rect::Rect = self.bound.clone().into();
+    canvas.set_clip_rect(clip);
+    for (row, words) in lines[first_line..].iter().enumerate() {
+      let line_chars = words.iter().map(|(word, _)| word.chars().count() + 1).sum::<usize>() - 1;
+      let line_width = line_chars as f32 * char_width;
+      let mut x = text_x + (text_width - line_width).min(0.0);
+      let y = self.bound.y1 + PADDING + line_height * row as f32;
+      
+      for (word, is_low_confidence) in words {
+        let word_width = font::text_width(word, TEXT_SCALE);
+        if *is_low_confidence {
+          let highlight = Rect {
+            x1: x,
+            y1: y,
+            x2: x + word_width,
+            y2: y + line_height
+          };
+          canvas.set_draw_color(Color::RGB(0xFF, 0xD0, 0x77));
+          let _ = canvas.fill_rect(Some(highlight.into()))
+            .map_err(|e| log::warn!("error calling canvas.fill_rect {e}"));
+        }
+        
+        font::draw_text(&mut canvas, word, x, y, TEXT_SCALE, text_color);
+        x += word_width + char_width;
+      }
+    }
+    canvas.set_clip_rect(None::<sdl3::rect::Rect>);
+  }
+}
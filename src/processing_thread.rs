@@ -1,21 +1,141 @@
-use std::{sync::{Mutex, atomic::{AtomicBool, Ordering}}, thread};
+use std::{sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}, thread};
 
 use image::{ImageBuffer, Rgb};
 use sdl3::pixels::PixelFormat;
 
-use crate::processor::{Processor, paddle_ocr::PaddleOcrProcessor};
+use crate::{candidates::{self, Candidates, Dictionary}, config::Config, processor::{BACKENDS, Backend, Processor, Recognition, find_backend}};
 
 pub static DO_SHUTDOWN: AtomicBool = AtomicBool::new(false);
-pub static CURRENTLY_RECOGNIZED: Mutex<Option<String>> = Mutex::new(None);
+pub static CURRENTLY_RECOGNIZED: Mutex<Option<Recognized>> = Mutex::new(None);
+// Latest failure, cleared once recognition works again
+pub static PROCESSING_ERROR: Mutex<Option<ProcessingError>> = Mutex::new(None);
+// Name of the backend in use, None until one is loaded
+pub static CURRENT_BACKEND: Mutex<Option<&'static str>> = Mutex::new(None);
+// Backend the UI wants to switch to, taken by the processing thread
+pub static REQUESTED_BACKEND: Mutex<Option<&'static str>> = Mutex::new(None);
+// Backend which failed to load when last switched to, the one in use
+// keeps working so this is not a processing error
+pub static FAILED_BACKEND: Mutex<Option<&'static str>> = Mutex::new(None);
+// Set by the UI after it replaced a word, the candidates of the current
+// recognition are found again
+pub static CANDIDATES_REQUESTED: AtomicBool = AtomicBool::new(false);
 
-pub fn main() {
+#[derive(Clone)]
+pub struct Recognized {
+  pub text: String,
+  // Regions and confidences the text was made of, shared with
+  // the preview so it is not copied every frame
+  pub recognition: Arc<Recognition>,
+  // Corrections for the candidate bar, None if there are none
+  pub candidates: Option<Candidates>,
+  // Update count of the writing canvas the text came from
+  pub update_id: u64
+}
+
+#[derive(Clone)]
+pub struct ProcessingError {
+  pub message: String,
+  // Update count of the writing canvas which failed,
+  // None if no engine could be loaded at all
+  pub update_id: Option<u64>
+}
+
+// Preferred backend first, falls back to the others in order
+fn load_processor(config: &Config) -> Option<(&'static Backend, Box<dyn Processor>)> {
+  let preferred = config.backend.as_deref().and_then(|name| {
+    let backend = find_backend(name);
+    if backend.is_none() {
+      let names: Vec<&str> = BACKENDS.iter().map(|backend| backend.name).collect();
+      log::warn!("Unknown backend '{name}', available are: {}", names.join(", "));
+    }
+    backend
+  });
+  
+  let others = BACKENDS.iter().filter(|backend| preferred.is_none_or(|preferred| preferred.name != backend.name));
+  for backend in preferred.into_iter().chain(others) {
+    match (backend.load)(config) {
+      Ok(processor) => {
+        log::info!("Using {} for recognition", backend.name);
+        return Some((backend, processor));
+      }
+      Err(e) => log::error!("Error loading {}: {e}, trying next backend", backend.name)
+    }
+  }
+  
+  None
+}
+
+// Switches to the backend the UI asked for, keeps the current one if it fails to load
+fn switch_backend(config: &Config, current: &mut Option<(&'static Backend, Box<dyn Processor>)>, name: &str) -> bool {
+  let Some(backend) = find_backend(name) else {
+    log::warn!("Unknown backend '{name}', not switching");
+    return false;
+  };
+  
+  match (backend.load)(config) {
+    Ok(processor) => {
+      log::info!("Switched to {} for recognition", backend.name);
+      *CURRENT_BACKEND.lock().unwrap() = Some(backend.name);
+      *current = Some((backend, processor));
+      *FAILED_BACKEND.lock().unwrap() = None;
+      *PROCESSING_ERROR.lock().unwrap() = None;
+      true
+    }
+    Err(e) => {
+      log::error!("Error loading {}: {e}, not switching", backend.name);
+      *FAILED_BACKEND.lock().unwrap() = Some(backend.name);
+      false
+    }
+  }
+}
+
+// Candidates for the current recognition, searching the dictionary
+// takes too long for the UI thread
+fn find_candidates_again(config: &Config, dictionary: &Dictionary) {
+  let Some(recognized) = CURRENTLY_RECOGNIZED.lock().unwrap().clone() else {
+    return;
+  };
+  let candidates = candidates::find(&recognized.recognition, dictionary, config.candidate_count);
+  
+  // Text may have been recognized again in the meantime
+  let mut current = CURRENTLY_RECOGNIZED.lock().unwrap();
+  if let Some(current) = current.as_mut().filter(|current| current.update_id == recognized.update_id && current.text == recognized.text) {
+    current.candidates = candidates;
+  }
+}
+
+pub fn main(config: Config, dictionary: Dictionary) {
   log::info!("Processing thread started");
   
   let mut latest_update_id = 0;
-  let mut processor = PaddleOcrProcessor::new();
+  let mut current = load_processor(&config);
+  match &current {
+    Some((backend, _)) => *CURRENT_BACKEND.lock().unwrap() = Some(backend.name),
+    None => {
+      log::error!("No recognition backend could be loaded");
+      *PROCESSING_ERROR.lock().unwrap() = Some(ProcessingError {
+        message: "No recognition backend could be loaded".into(),
+        update_id: None
+      });
+    }
+  }
   
   while DO_SHUTDOWN.load(Ordering::Relaxed) == false {
     thread::park();
+    let requested = REQUESTED_BACKEND.lock().unwrap().take();
+    if let Some(name) = requested && switch_backend(&config, &mut current, name) {
+      // Writing on the canvas is recognized again with the new backend
+      latest_update_id = 0;
+    }
+    
+    if CANDIDATES_REQUESTED.swap(false, Ordering::Relaxed) {
+      find_candidates_again(&config, &dictionary);
+    }
+    
+    let Some((_, processor)) = current.as_mut() else {
+      continue;
+    };
+    
     let Some((pixels, update_id)) = crate::get_pixels() else {
       // Buffer is not ready yet
       println!("Buffer not ready");
@@ -29,10 +149,36 @@ pub fn main() {
     assert!(matches!(pixels.format, PixelFormat::RGB24));
     
     let image = ImageBuffer::<Rgb<u8>, &[u8]>::from_raw(u32::try_from(pixels.width).unwrap(), u32::try_from(pixels.height).unwrap(), &pixels.data).unwrap();
-    let recognized = processor.detect(&image);
-    log::info!("Text recognized: {}", recognized.trim());
+    let mut recognition = match processor.detect(&image) {
+      Ok(recognition) => recognition,
+      Err(e) => {
+        log::error!("Error recognizing text: {e}");
+        *PROCESSING_ERROR.lock().unwrap() = Some(ProcessingError {
+          message: e.to_string(),
+          update_id: Some(update_id)
+        });
+        continue;
+      }
+    };
+    recognition.apply_confidence(config.confidence_threshold, config.low_confidence);
+    log::info!("Text recognized: {}", recognition.text.trim());
+    for region in &recognition.regions {
+      let bound = &region.bounding_box;
+      log::debug!("Region '{}' at ({}, {}) to ({}, {}), confidence {:.2}", region.text, bound.x1, bound.y1, bound.x2, bound.y2, region.confidence);
+    }
+    
+    let candidates = candidates::find(&recognition, &dictionary, config.candidate_count);
+    if let Some(candidates) = &candidates {
+      log::info!("Candidates for '{}': {}", recognition.regions[candidates.region].text, candidates.texts.join(", "));
+    }
     
-    *CURRENTLY_RECOGNIZED.lock().unwrap() = Some(recognized);
+    *PROCESSING_ERROR.lock().unwrap() = None;
+    *CURRENTLY_RECOGNIZED.lock().unwrap() = Some(Recognized {
+      text: recognition.text.clone(),
+      candidates,
+      recognition: Arc::new(recognition),
+      update_id
+    });
   }
   
   log::info!("Processing thread stapped");
@@ -1,46 +1,298 @@
-use image::{ImageBuffer, Rgb, codecs::png::PngEncoder};
-use leptess::LepTess;
+use std::{ffi::{CStr, CString, c_char}, os::unix::ffi::OsStrExt, ptr};
 
-use crate::processor::Processor;
+use image::{ImageBuffer, Rgb};
+use leptess::{Variable, capi, tesseract::{TessInitError, TessSetVariableError}};
 
+use crate::{config::Config, data_dir, processor::{Alternative, Processor, ProcessorError, Recognition, TextRegion}, shapes::Rect};
+
+// Files which must be in the tessdata directory
+const TESSDATA_FILES: &[&str] = &["eng.traineddata"];
+
+// Tesseract's confidences are percentages
+const CONFIDENCE_SCALE: f32 = 100.0;
+
+// Tesseract is used through its C API, the wrapper of leptess
+// has no way to walk the results symbol by symbol
 pub struct LepTessProcessor {
-  api: LepTess,
-  data_buffer: Vec<u8>
+  api: *mut capi::TessBaseAPI
 }
 
 impl LepTessProcessor {
-  #[expect(unused)]
-  pub fn new() -> Self {
-    let mut result = Self {
-      api: LepTess::new(Some("./tessdata"), "eng").unwrap(),
-      data_buffer: Vec::new()
+  // Language data is searched for in tessdata directories, STYLUS_WRITING_TESSDATA
+  // or tessdata in the config can point to one
+  pub fn new(config: &Config) -> Result<Self, ProcessorError> {
+    let dir = data_dir::find("tessdata", TESSDATA_FILES, "STYLUS_WRITING_TESSDATA", config.tessdata.as_deref())
+      .map_err(|tried| ProcessorError::FilesNotFound { files: TESSDATA_FILES, tried })?;
+    log::info!("Loading Tesseract data from {}", dir.display());
+    
+    // Dropping the result frees the API again if anything below fails
+    let result = Self {
+      api: unsafe { capi::TessBaseAPICreate() }
     };
     
-    result.api.set_variable(leptess::Variable::TesseditOcrEngineMode, "lstm").unwrap();
-    result.api.set_variable(leptess::Variable::SuperscriptScaledownRatio, "3.0").unwrap();
-    result.api.set_variable(leptess::Variable::SubscriptMaxYTop, "3.0").unwrap();
-    result.api.set_variable(leptess::Variable::SuperscriptMinYBottom, "3.0").unwrap();
-    result.api.set_variable(leptess::Variable::TesseditZeroRejection, "true").unwrap();
-    result.api.set_variable(leptess::Variable::TesseditZeroKelvinRejection, "true").unwrap();
-    result.api.set_variable(leptess::Variable::TesseditUnrejAnyWd, "true").unwrap();
-    result.api.set_variable(leptess::Variable::TesseditPreserveMinWdLen, "0").unwrap();
-    result.api.set_variable(leptess::Variable::BlandUnrej, "true").unwrap();
-    result.api.set_variable(leptess::Variable::SuspectLevel, "80").unwrap();
-    result.api.set_variable(leptess::Variable::TesseditParallelize, "true").unwrap();
+    let dir_path = CString::new(dir.as_os_str().as_bytes())
+      .map_err(|_| ProcessorError::TesseractPath(dir.clone()))?;
+    let code = unsafe { capi::TessBaseAPIInit3(result.api, dir_path.as_ptr(), c"eng".as_ptr()) };
+    if code != 0 {
+      return Err(ProcessorError::TesseractInit(TessInitError { code }));
+    }
+    
+    let variables = [
+      (Variable::TesseditOcrEngineMode, "lstm"),
+      (Variable::SuperscriptScaledownRatio, "3.0"),
+      (Variable::SubscriptMaxYTop, "3.0"),
+      (Variable::SuperscriptMinYBottom, "3.0"),
+      (Variable::TesseditZeroRejection, "true"),
+      (Variable::TesseditZeroKelvinRejection, "true"),
+      (Variable::TesseditUnrejAnyWd, "true"),
+      (Variable::TesseditPreserveMinWdLen, "0"),
+      (Variable::BlandUnrej, "true"),
+      (Variable::SuspectLevel, "80"),
+      (Variable::TesseditParallelize, "true"),
+      // Other readings of every symbol, for the alternatives
+      (Variable::LstmChoiceMode, "2")
+    ];
+    for (variable, value) in variables {
+      let value = CString::new(value).unwrap();
+      if unsafe { capi::TessBaseAPISetVariable(result.api, variable.as_cstr().as_ptr(), value.as_ptr()) } == 0 {
+        return Err(ProcessorError::TesseractVariable(variable, TessSetVariableError()));
+      }
+    }
     
-    result
+    Ok(result)
+  }
+}
+
+impl Drop for LepTessProcessor {
+  fn drop(&mut self) {
+    unsafe { capi::TessBaseAPIDelete(self.api) };
   }
 }
 
 impl Processor for LepTessProcessor {
-  fn detect(&mut self, img: &ImageBuffer<Rgb<u8>, &[u8]>) -> String {
-    self.data_buffer.clear();
-    img.write_with_encoder(PngEncoder::new(&mut self.data_buffer)).unwrap();
+  fn detect(&mut self, img: &ImageBuffer<Rgb<u8>, &[u8]>) -> Result<Recognition, ProcessorError> {
+    let (width, height) = (img.width() as i32, img.height() as i32);
+    // Tesseract copies the pixels, they only have to live through the call
+    unsafe { capi::TessBaseAPISetImage(self.api, img.as_raw().as_ptr(), width, height, 3, width * 3) };
+    
+    if unsafe { capi::TessBaseAPIRecognize(self.api, ptr::null_mut()) } != 0 {
+      return Err(ProcessorError::TesseractRecognize);
+    }
+    
+    // Lines are put together the same way for every backend
+    let words = read_words(self.api)?;
+    Ok(Recognition::from_regions(words.into_iter().filter_map(word_region).collect()))
+  }
+}
+
+// Word as Tesseract read it, confidences are percentages
+struct TessWord {
+  text: String,
+  bounding_box: Rect,
+  confidence: f32,
+  symbols: Vec<TessSymbol>
+}
+
+// One character of a word, sometimes more for ligatures
+struct TessSymbol {
+  text: String,
+  confidence: f32,
+  // Other readings of the symbol, may include the text itself
+  choices: Vec<(String, f32)>
+}
+
+// Walks the recognized symbols, starting a word at every word beginning
+fn read_words(api: *mut capi::TessBaseAPI) -> Result<Vec<TessWord>, ProcessorError> {
+  let iterator = unsafe { capi::TessBaseAPIGetIterator(api) };
+  if iterator.is_null() {
+    return Ok(Vec::new());
+  }
+  
+  let words = unsafe { read_words_from(iterator) };
+  unsafe { capi::TessResultIteratorDelete(iterator) };
+  words
+}
+
+// Iterator must not be null, it is left at the end
+unsafe fn read_words_from(iterator: *mut capi::TessResultIterator) -> Result<Vec<TessWord>, ProcessorError> {
+  let word_level = capi::TessPageIteratorLevel_RIL_WORD;
+  let symbol_level = capi::TessPageIteratorLevel_RIL_SYMBOL;
+  let page_iterator = unsafe { capi::TessResultIteratorGetPageIteratorConst(iterator) };
+  
+  let mut words: Vec<TessWord> = Vec::new();
+  loop {
+    if unsafe { capi::TessPageIteratorIsAtBeginningOf(page_iterator, word_level) } != 0 || words.is_empty() {
+      let (mut left, mut top, mut right, mut bottom) = (0, 0, 0, 0);
+      unsafe { capi::TessPageIteratorBoundingBox(page_iterator, word_level, &mut left, &mut top, &mut right, &mut bottom) };
+      words.push(TessWord {
+        text: unsafe { take_text(capi::TessResultIteratorGetUTF8Text(iterator, word_level)) }?,
+        bounding_box: Rect {
+          x1: left as f32,
+          y1: top as f32,
+          x2: right as f32,
+          y2: bottom as f32
+        },
+        confidence: unsafe { capi::TessResultIteratorConfidence(iterator, word_level) },
+        symbols: Vec::new()
+      });
+    }
+    
+    let mut choices = Vec::new();
+    let choice_iterator = unsafe { capi::TessResultIteratorGetChoiceIterator(iterator) };
+    if !choice_iterator.is_null() {
+      loop {
+        let text = unsafe { capi::TessChoiceIteratorGetUTF8Text(choice_iterator) };
+        if !text.is_null() {
+          let text = unsafe { CStr::from_ptr(text) }.to_string_lossy().into_owned();
+          choices.push((text, unsafe { capi::TessChoiceIteratorConfidence(choice_iterator) }));
+        }
+        
+        if unsafe { capi::TessChoiceIteratorNext(choice_iterator) } == 0 {
+          break;
+        }
+      }
+      unsafe { capi::TessChoiceIteratorDelete(choice_iterator) };
+    }
     
-    self.api.set_image_from_mem(&self.data_buffer).unwrap();
+    let symbol = TessSymbol {
+      text: unsafe { take_text(capi::TessResultIteratorGetUTF8Text(iterator, symbol_level)) }?,
+      confidence: unsafe { capi::TessResultIteratorConfidence(iterator, symbol_level) },
+      choices
+    };
+    words.last_mut().unwrap().symbols.push(symbol);
     
-    self.api.get_utf8_text().unwrap()
+    if unsafe { capi::TessResultIteratorNext(iterator, symbol_level) } == 0 {
+      break;
+    }
+  }
+  
+  Ok(words)
+}
+
+// Copies and frees text returned by Tesseract, which may be null
+unsafe fn take_text(text: *mut c_char) -> Result<String, ProcessorError> {
+  if text.is_null() {
+    return Ok(String::new());
   }
+  
+  let result = unsafe { CStr::from_ptr(text) }.to_str().map(str::to_string);
+  unsafe { capi::TessDeleteText(text) };
+  Ok(result?)
 }
 
+// Region for a word, None if it has no text
+fn word_region(word: TessWord) -> Option<TextRegion> {
+  let text = word.text.trim();
+  if text.is_empty() {
+    return None;
+  }
+  
+  let scale = |confidence: f32| (confidence / CONFIDENCE_SCALE).clamp(0.0, 1.0);
+  
+  // Symbols share their confidence between their chars, left
+  // out if they do not add up to the text of the word
+  let mut char_confidences = Vec::new();
+  for symbol in &word.symbols {
+    char_confidences.extend(symbol.text.chars().map(|_| scale(symbol.confidence)));
+  }
+  let symbol_text: String = word.symbols.iter().map(|symbol| symbol.text.as_str()).collect();
+  let char_confidences = (symbol_text == text).then_some(char_confidences);
+  
+  // Every other reading of a symbol makes an alternative word, as
+  // confident as its least confident symbol
+  let mut alternatives: Vec<Alternative> = Vec::new();
+  if char_confidences.is_some() {
+    for (index, symbol) in word.symbols.iter().enumerate() {
+      let others_confidence = word.symbols.iter().enumerate()
+        .filter(|(other, _)| *other != index)
+        .map(|(_, other)| other.confidence)
+        .fold(CONFIDENCE_SCALE, f32::min);
+      
+      for (choice, confidence) in &symbol.choices {
+        if choice.is_empty() || *choice == symbol.text {
+          continue;
+        }
+        
+        let alternative: String = word.symbols.iter().enumerate()
+          .map(|(other, other_symbol)| if other == index { choice.as_str() } else { other_symbol.text.as_str() })
+          .collect();
+        let confidence = scale(confidence.min(others_confidence));
+        match alternatives.iter_mut().find(|existing| existing.text == alternative) {
+          Some(existing) => existing.confidence = existing.confidence.max(confidence),
+          None => alternatives.push(Alternative { text: alternative, confidence })
+        }
+      }
+    }
+    alternatives.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+  }
+  
+  Some(TextRegion {
+    char_confidences,
+    alternatives,
+    ..TextRegion::new(text, scale(word.confidence), word.bounding_box)
+  })
+}
 
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  fn symbol(text: &str, confidence: f32, choices: &[(&str, f32)]) -> TessSymbol {
+    TessSymbol {
+      text: text.to_string(),
+      confidence,
+      choices: choices.iter().map(|(text, confidence)| (text.to_string(), *confidence)).collect()
+    }
+  }
+  
+  fn word(text: &str, confidence: f32, symbols: Vec<TessSymbol>) -> TessWord {
+    TessWord {
+      text: text.to_string(),
+      bounding_box: Rect { x1: 10.0, y1: 20.0, x2: 50.0, y2: 40.0 },
+      confidence,
+      symbols
+    }
+  }
+  
+  #[test]
+  fn word_region_scales_confidences() {
+    let region = word_region(word("hi", 80.0, vec![symbol("h", 90.0, &[]), symbol("i", 80.0, &[])])).unwrap();
+    assert_eq!(region.text, "hi");
+    assert_eq!(region.confidence, 0.8);
+    assert_eq!(region.char_confidences, Some(vec![0.9, 0.8]));
+    assert_eq!((region.bounding_box.x1, region.bounding_box.y2), (10.0, 40.0));
+  }
+  
+  #[test]
+  fn word_region_skips_blank_words() {
+    assert!(word_region(word(" \n", 95.0, vec![symbol(" ", 95.0, &[])])).is_none());
+  }
+  
+  #[test]
+  fn word_region_shares_confidence_of_ligatures() {
+    let region = word_region(word("fit", 70.0, vec![symbol("fi", 60.0, &[]), symbol("t", 90.0, &[])])).unwrap();
+    assert_eq!(region.char_confidences, Some(vec![0.6, 0.6, 0.9]));
+  }
+  
+  #[test]
+  fn word_region_drops_char_confidences_not_matching_text() {
+    let region = word_region(word("cat", 70.0, vec![symbol("c", 60.0, &[]), symbol("a", 90.0, &[])])).unwrap();
+    assert_eq!(region.char_confidences, None);
+    assert!(region.alternatives.is_empty());
+  }
+  
+  #[test]
+  fn word_region_makes_alternatives_from_symbol_choices() {
+    let region = word_region(word("cat", 40.0, vec![
+      symbol("c", 90.0, &[("c", 90.0), ("e", 20.0)]),
+      symbol("a", 40.0, &[("a", 40.0), ("o", 35.0), ("u", 10.0)]),
+      symbol("t", 80.0, &[])
+    ])).unwrap();
+    
+    let alternatives: Vec<(&str, f32)> = region.alternatives.iter()
+      .map(|alternative| (alternative.text.as_str(), alternative.confidence))
+      .collect();
+    // Alternative for c is held back by the unsure a
+    assert_eq!(alternatives, vec![("cot", 0.35), ("eat", 0.2), ("cut", 0.1)]);
+  }
+}
@@ -1,9 +1,357 @@
+use std::{fmt::Display, path::PathBuf, str::Utf8Error};
+
 use image::{ImageBuffer, Rgb};
+use ::leptess::tesseract::{TessInitError, TessSetVariableError};
+use oar_ocr::prelude::OCRError;
+
+use crate::{config::{Config, LowConfidence}, processor::{leptess::LepTessProcessor, paddle_ocr::PaddleOcrProcessor}, shapes::Rect};
 
 pub mod leptess;
 pub mod paddle_ocr;
 
+pub struct Backend {
+  // Used in the config and on the command line
+  pub name: &'static str,
+  pub load: fn(&Config) -> Result<Box<dyn Processor>, ProcessorError>
+}
+
+// Every recognition engine, the first one is the default
+pub static BACKENDS: [Backend; 2] = [
+  Backend {
+    name: "paddle",
+    load: |config| Ok(Box::new(PaddleOcrProcessor::new(config)?))
+  },
+  Backend {
+    name: "tesseract",
+    load: |config| Ok(Box::new(LepTessProcessor::new(config)?))
+  }
+];
+
+pub fn find_backend(name: &str) -> Option<&'static Backend> {
+  BACKENDS.iter().find(|backend| backend.name == name)
+}
+
 pub trait Processor {
-  fn detect(&mut self, image: &ImageBuffer<Rgb<u8>, &[u8]>) -> String;
+  fn detect(&mut self, image: &ImageBuffer<Rgb<u8>, &[u8]>) -> Result<Recognition, ProcessorError>;
+}
+
+#[derive(Debug)]
+pub enum ProcessorError {
+  // No directory had all the files the engine needs
+  FilesNotFound { files: &'static [&'static str], tried: Vec<PathBuf> },
+  // Loading the models or running them failed
+  Paddle(OCRError),
+  // Tesseract's C API cannot take a path with a NUL byte in it
+  TesseractPath(PathBuf),
+  // Tesseract could not load the language data
+  TesseractInit(TessInitError),
+  TesseractVariable(::leptess::Variable, TessSetVariableError),
+  // Tesseract failed to recognize the image
+  TesseractRecognize,
+  TesseractText(Utf8Error),
+  // Size of the pixel data does not match the image size
+  ImageSize { width: u32, height: u32, length: usize }
+}
+
+impl Display for ProcessorError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ProcessorError::FilesNotFound { files, tried } => {
+        write!(f, "{} not found, looked in:", files.join(", "))?;
+        for dir in tried {
+          write!(f, "\n  {}", dir.display())?;
+        }
+        Ok(())
+      }
+      ProcessorError::Paddle(e) => write!(f, "Paddle OCR failed: {e}"),
+      ProcessorError::TesseractPath(path) => write!(f, "Tesseract cannot load data from {}, the path has a NUL byte", path.display()),
+      ProcessorError::TesseractInit(e) => write!(f, "Tesseract could not be loaded: {e}"),
+      ProcessorError::TesseractVariable(variable, e) => write!(f, "Tesseract variable {variable:?} could not be set: {e}"),
+      ProcessorError::TesseractRecognize => write!(f, "Tesseract could not recognize the image"),
+      ProcessorError::TesseractText(e) => write!(f, "Tesseract returned invalid text: {e}"),
+      ProcessorError::ImageSize { width, height, length } => write!(f, "{length} bytes of pixels do not make a {width}x{height} image")
+    }
+  }
+}
+
+impl std::error::Error for ProcessorError {}
+
+impl From<OCRError> for ProcessorError {
+  fn from(value: OCRError) -> Self {
+    ProcessorError::Paddle(value)
+  }
+}
+
+impl From<TessInitError> for ProcessorError {
+  fn from(value: TessInitError) -> Self {
+    ProcessorError::TesseractInit(value)
+  }
+}
+
+impl From<Utf8Error> for ProcessorError {
+  fn from(value: Utf8Error) -> Self {
+    ProcessorError::TesseractText(value)
+  }
 }
 
+// Part of the shorter region's height two regions must overlap
+// vertically to be on the same line
+const LINE_MIN_OVERLAP: f32 = 0.5;
+
+#[derive(Clone, Default)]
+pub struct Recognition {
+  // Regions in reading order, words separated by spaces and lines by newlines
+  pub text: String,
+  // In reading order, top line first and left to right within the line
+  pub regions: Vec<TextRegion>
+}
+
+impl Recognition {
+  // Regions can be in any order, they are split into words,
+  // sorted and grouped into lines
+  pub fn from_regions(regions: Vec<TextRegion>) -> Self {
+    let mut regions: Vec<TextRegion> = regions.into_iter().flat_map(TextRegion::into_words).collect();
+    sort_reading_order(&mut regions);
+    let text = join_lines(&regions);
+    
+    Self {
+      text,
+      regions
+    }
+  }
+  
+  // Puts other text in place of a region, like a correction by the
+  // user, which makes the region fully confident
+  pub fn replace_region(&mut self, index: usize, text: String) {
+    let region = &mut self.regions[index];
+    region.text = text;
+    region.confidence = 1.0;
+    region.is_low_confidence = false;
+    region.alternatives.clear();
+    self.text = join_lines(&self.regions);
+  }
+  
+  // Marks regions under the threshold, and leaves them out if they should be dropped
+  pub fn apply_confidence(&mut self, threshold: f32, mode: LowConfidence) {
+    for region in self.regions.iter_mut() {
+      region.is_low_confidence = region.confidence < threshold;
+    }
+    
+    if mode == LowConfidence::Keep || !self.regions.iter().any(|region| region.is_low_confidence) {
+      return;
+    }
+    
+    for region in self.regions.iter().filter(|region| region.is_low_confidence) {
+      log::info!("Not confident enough, dropping '{}' (confidence: {:.2} < {threshold:.2})", region.text, region.confidence);
+    }
+    self.regions.retain(|region| !region.is_low_confidence);
+    
+    // Text is put together again without the dropped regions
+    self.text = join_lines(&self.regions);
+  }
+}
+
+// Groups regions into lines by how much they overlap vertically,
+// then sorts lines top to bottom and regions in them left to right
+fn sort_reading_order(regions: &mut [TextRegion]) {
+  let center = |region: &TextRegion| (region.bounding_box.y1 + region.bounding_box.y2) / 2.0;
+  regions.sort_by(|a, b| center(a).total_cmp(&center(b)));
+  
+  // .0 = top of current line
+  // .1 = bottom of current line
+  let mut line_span: Option<(f32, f32)> = None;
+  let mut line = 0;
+  for region in regions.iter_mut() {
+    let (top, bottom) = (region.bounding_box.y1, region.bounding_box.y2);
+    if let Some((line_top, line_bottom)) = line_span.as_mut() {
+      let overlap = bottom.min(*line_bottom) - top.max(*line_top);
+      let shorter = (bottom - top).min(*line_bottom - *line_top);
+      if shorter > 0.0 && overlap >= shorter * LINE_MIN_OVERLAP {
+        *line_top = line_top.min(top);
+        *line_bottom = line_bottom.max(bottom);
+      } else {
+        line += 1;
+        line_span = Some((top, bottom));
+      }
+    } else {
+      line_span = Some((top, bottom));
+    }
+    region.line = line;
+  }
+  
+  regions.sort_by(|a, b| a.line.cmp(&b.line).then(a.bounding_box.x1.total_cmp(&b.bounding_box.x1)));
+}
+
+// Regions have to be in reading order
+fn join_lines(regions: &[TextRegion]) -> String {
+  let mut text = String::new();
+  for (i, region) in regions.iter().enumerate() {
+    if i > 0 {
+      text.push(if regions[i - 1].line == region.line { ' ' } else { '\n' });
+    }
+    text.push_str(&region.text);
+  }
+  text
+}
+
+// A word or line found by the backend
+#[derive(Clone)]
+pub struct TextRegion {
+  pub text: String,
+  // Index of the line the region is on, counted from the top
+  pub line: usize,
+  // In pixels of the image given to the processor
+  pub bounding_box: Rect,
+  // 0.0 to 1.0
+  pub confidence: f32,
+  // Under the configured threshold, see Recognition::apply_confidence
+  pub is_low_confidence: bool,
+  // One for each char in text, None if the backend does not report it
+  pub char_confidences: Option<Vec<f32>>,
+  // Other readings of the region, best first
+  pub alternatives: Vec<Alternative>
+}
+
+impl TextRegion {
+  // Region on the first line with no details, backends fill in what they report
+  pub fn new(text: &str, confidence: f32, bounding_box: Rect) -> Self {
+    Self {
+      text: text.to_string(),
+      line: 0,
+      bounding_box,
+      confidence,
+      is_low_confidence: false,
+      char_confidences: None,
+      alternatives: Vec::new()
+    }
+  }
+  
+  // One region for each word of the text, the bounding box is shared
+  // out as if every char were as wide
+  fn into_words(self) -> Vec<TextRegion> {
+    if !self.text.contains(char::is_whitespace) {
+      return vec![self];
+    }
+    
+    let chars: Vec<char> = self.text.chars().collect();
+    let Rect { x1, y1, x2, y2 } = self.bounding_box;
+    let char_width = (x2 - x1) / chars.len() as f32;
+    let char_confidences = self.char_confidences.filter(|confidences| confidences.len() == chars.len());
+    
+    let mut words = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+      if chars[start].is_whitespace() {
+        start += 1;
+        continue;
+      }
+      
+      let end = (start..chars.len()).find(|i| chars[*i].is_whitespace()).unwrap_or(chars.len());
+      let text: String = chars[start..end].iter().collect();
+      let bounding_box = Rect {
+        x1: x1 + start as f32 * char_width,
+        y1,
+        x2: x1 + end as f32 * char_width,
+        y2
+      };
+      // Alternatives are readings of the whole text
+      words.push(TextRegion {
+        line: self.line,
+        is_low_confidence: self.is_low_confidence,
+        char_confidences: char_confidences.as_ref().map(|confidences| confidences[start..end].to_vec()),
+        ..TextRegion::new(&text, self.confidence, bounding_box)
+      });
+      start = end;
+    }
+    
+    words
+  }
+}
+
+#[derive(Clone)]
+pub struct Alternative {
+  pub text: String,
+  pub confidence: f32
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  fn texts(regions: &[TextRegion]) -> Vec<(&str, usize)> {
+    regions.iter().map(|region| (region.text.as_str(), region.line)).collect()
+  }
+  
+  #[test]
+  fn regions_are_sorted_into_lines() {
+    let mut regions = vec![
+      TextRegion::new("world", 0.9, Rect { x1: 60.0, y1: 12.0, x2: 110.0, y2: 42.0 }),
+      TextRegion::new("again", 0.9, Rect { x1: 0.0, y1: 60.0, x2: 50.0, y2: 90.0 }),
+      TextRegion::new("hello", 0.9, Rect { x1: 0.0, y1: 10.0, x2: 50.0, y2: 40.0 })
+    ];
+    sort_reading_order(&mut regions);
+    assert_eq!(texts(&regions), vec![("hello", 0), ("world", 0), ("again", 1)]);
+  }
+  
+  #[test]
+  fn slanted_line_stays_together() {
+    // Each word a bit lower than the one before, like writing uphill
+    let mut regions = vec![
+      TextRegion::new("c", 0.9, Rect { x1: 80.0, y1: 30.0, x2: 110.0, y2: 60.0 }),
+      TextRegion::new("a", 0.9, Rect { x1: 0.0, y1: 10.0, x2: 30.0, y2: 40.0 }),
+      TextRegion::new("b", 0.9, Rect { x1: 40.0, y1: 20.0, x2: 70.0, y2: 50.0 })
+    ];
+    sort_reading_order(&mut regions);
+    assert_eq!(texts(&regions), vec![("a", 0), ("b", 0), ("c", 0)]);
+  }
+  
+  #[test]
+  fn small_overlap_starts_new_line() {
+    let mut regions = vec![
+      TextRegion::new("below", 0.9, Rect { x1: 0.0, y1: 35.0, x2: 50.0, y2: 65.0 }),
+      TextRegion::new("above", 0.9, Rect { x1: 0.0, y1: 0.0, x2: 50.0, y2: 40.0 })
+    ];
+    sort_reading_order(&mut regions);
+    assert_eq!(texts(&regions), vec![("above", 0), ("below", 1)]);
+  }
+  
+  #[test]
+  fn lines_are_joined_with_newlines() {
+    let mut regions = vec![TextRegion::new("one", 0.9, Rect { x1: 0.0, y1: 0.0, x2: 1.0, y2: 1.0 }), TextRegion::new("two", 0.9, Rect { x1: 0.0, y1: 0.0, x2: 1.0, y2: 1.0 }), TextRegion::new("three", 0.9, Rect { x1: 0.0, y1: 0.0, x2: 1.0, y2: 1.0 })];
+    regions[2].line = 1;
+    assert_eq!(join_lines(&regions), "one two\nthree");
+    assert_eq!(join_lines(&[]), "");
+  }
+  
+  #[test]
+  fn lines_are_split_into_words() {
+    let mut line = TextRegion::new("ab  cd e", 0.9, Rect { x1: 0.0, y1: 10.0, x2: 80.0, y2: 30.0 });
+    line.char_confidences = Some(vec![0.1, 0.2, 0.0, 0.0, 0.3, 0.4, 0.0, 0.5]);
+    let words = line.into_words();
+    
+    let texts: Vec<&str> = words.iter().map(|word| word.text.as_str()).collect();
+    assert_eq!(texts, vec!["ab", "cd", "e"]);
+    assert_eq!((words[1].bounding_box.x1, words[1].bounding_box.x2), (40.0, 60.0));
+    assert_eq!((words[1].bounding_box.y1, words[1].bounding_box.y2), (10.0, 30.0));
+    assert_eq!(words[1].char_confidences, Some(vec![0.3, 0.4]));
+    assert!(words.iter().all(|word| word.confidence == 0.9));
+  }
+  
+  #[test]
+  fn recognition_is_made_of_words() {
+    let recognition = Recognition::from_regions(vec![TextRegion::new("hello world", 0.9, Rect { x1: 0.0, y1: 0.0, x2: 110.0, y2: 30.0 })]);
+    assert_eq!(recognition.regions.len(), 2);
+    assert_eq!(recognition.text, "hello world");
+  }
+  
+  #[test]
+  fn dropped_regions_leave_text_without_them() {
+    let mut recognition = Recognition::from_regions(vec![
+      TextRegion::new("sure", 0.9, Rect { x1: 0.0, y1: 0.0, x2: 40.0, y2: 30.0 }),
+      TextRegion::new("unsure", 0.9, Rect { x1: 50.0, y1: 0.0, x2: 90.0, y2: 30.0 })
+    ]);
+    recognition.regions[1].confidence = 0.3;
+    recognition.apply_confidence(0.5, LowConfidence::Drop);
+    assert_eq!(recognition.text, "sure");
+  }
+}
@@ -1,57 +1,86 @@
 use image::RgbImage;
 use oar_ocr::prelude::{OAROCR, OAROCRBuilder};
 
-use crate::processor::Processor;
+use crate::{config::Config, data_dir, processor::{Processor, ProcessorError, Recognition, TextRegion}, shapes::Rect};
+
+// Files which must be in the models directory
+const MODEL_FILES: &[&str] = &[
+  "det.onnx",
+  "rec.onnx",
+  "dict.txt",
+  "textline.onnx",
+  "orientation.onnx",
+  "unwarping.onnx"
+];
 
 pub struct PaddleOcrProcessor {
   oar: OAROCR
 }
 
 impl PaddleOcrProcessor {
-  pub fn new() -> Self {
-    Self {
+  // Models are searched for in paddle-paddle directories, STYLUS_WRITING_PADDLE_MODELS
+  // or paddle_models in the config can point to one
+  pub fn new(config: &Config) -> Result<Self, ProcessorError> {
+    let dir = data_dir::find("paddle-paddle", MODEL_FILES, "STYLUS_WRITING_PADDLE_MODELS", config.paddle_models.as_deref())
+      .map_err(|tried| ProcessorError::FilesNotFound { files: MODEL_FILES, tried })?;
+    log::info!("Loading Paddle OCR models from {}", dir.display());
+    let model = |file: &str| dir.join(file).to_string_lossy().into_owned();
+    
+    Ok(Self {
       oar: OAROCRBuilder::new(
-          "./paddle-paddle/det.onnx".into(),
-          "./paddle-paddle/rec.onnx".into(),
-          "./paddle-paddle/dict.txt".into()
+          model("det.onnx"),
+          model("rec.onnx"),
+          model("dict.txt")
         )
         .with_high_performance()
-        .textline_orientation_classify_model_name("./paddle-paddle/textline.onnx".into())
-        .doc_orientation_classify_model_name("./paddle-paddle/orientation.onnx".into())
-        .doc_unwarping_model_name("./paddle-paddle/unwarping.onnx".into())
-        .build()
-        .unwrap()
-    }
+        .textline_orientation_classify_model_name(model("textline.onnx"))
+        .doc_orientation_classify_model_name(model("orientation.onnx"))
+        .doc_unwarping_model_name(model("unwarping.onnx"))
+        .build()?
+    })
   }
 }
 
 impl Processor for PaddleOcrProcessor {
-  fn detect(&mut self, image: &image::ImageBuffer<image::Rgb<u8>, &[u8]>) -> String {
+  fn detect(&mut self, image: &image::ImageBuffer<image::Rgb<u8>, &[u8]>) -> Result<Recognition, ProcessorError> {
     let mut rgb = Vec::new();
     rgb.extend_from_slice(image.as_raw());
-    let rgb = RgbImage::from_raw(image.width(), image.height(), rgb).unwrap();
+    let length = rgb.len();
+    let rgb = RgbImage::from_raw(image.width(), image.height(), rgb)
+      .ok_or(ProcessorError::ImageSize { width: image.width(), height: image.height(), length })?;
     
-    let result = self.oar.predict(&[rgb]).unwrap();
+    let result = self.oar.predict(&[rgb])?;
     
-    let mut string = String::new();
+    let mut regions = Vec::new();
     result.iter().for_each(|result| {
-      for text in &result.text_regions {
-        if let (Some(text), Some(confidence)) = (&text.text, &text.confidence) {
-          if *confidence < 0.70 {
-            println!("AI not confidence enough not adding '{text}' (confidence: {confidence} < 70.0)");
-          } else {
-            if !string.is_empty() {
-              string.push(' ');
-            }
-            string.push_str(text.trim());
+      for region in &result.text_regions {
+        if let (Some(text), Some(confidence)) = (&region.text, &region.confidence) {
+          if text.trim().is_empty() {
+            continue;
+          }
+          
+          // Detector gives a polygon, the region is the box around it
+          let mut bounding_box = Rect {
+            x1: f32::INFINITY,
+            y1: f32::INFINITY,
+            x2: f32::NEG_INFINITY,
+            y2: f32::NEG_INFINITY
+          };
+          for point in &region.bounding_box.points {
+            bounding_box.x1 = bounding_box.x1.min(point.x);
+            bounding_box.y1 = bounding_box.y1.min(point.y);
+            bounding_box.x2 = bounding_box.x2.max(point.x);
+            bounding_box.y2 = bounding_box.y2.max(point.y);
           }
+          
+          // Recognizer only reports confidence of the whole region and
+          // no alternatives, oar-ocr averages the scores of the steps
+          regions.push(TextRegion::new(text.trim(), *confidence, bounding_box));
         }
       }
     });
     
-    string
+    // Detector returns regions in no particular order
+    Ok(Recognition::from_regions(regions))
   }
 }
-
-
-
@@ -1,16 +1,99 @@
-use std::fmt::Display;
+use std::{cell::OnceCell, fmt::Display, rc::Rc};
 
 use sdl3::render::{FPoint, FRect};
 
+use crate::{ink::InkMesh, input::PointerId};
+
+// One pen down to pen up, points are in the order they were drawn
 #[derive(Clone)]
 pub struct Stroke {
-  pub start: Point,
-  pub end: Point
+  pub pen: PointerId,
+  // Changed only through push and last_point_mut, which drop the mesh
+  pub points: Vec<InkPoint>,
+  // Made when first drawn, shared with the copies of the stroke in the history
+  mesh: Rc<OnceCell<InkMesh>>
 }
 
 impl Stroke {
+  pub fn new(pen: PointerId, first: InkPoint) -> Self {
+    Self::from_points(pen, vec![first])
+  }
+  
+  pub fn from_points(pen: PointerId, points: Vec<InkPoint>) -> Self {
+    Self {
+      pen,
+      points,
+      mesh: Rc::default()
+    }
+  }
+  
+  pub fn push(&mut self, point: InkPoint) {
+    self.mesh = Rc::default();
+    self.points.push(point);
+  }
+  
+  pub fn last_point_mut(&mut self) -> &mut InkPoint {
+    self.mesh = Rc::default();
+    self.points.last_mut().unwrap()
+  }
+  
+  pub fn mesh(&self, make: impl FnOnce(&[InkPoint]) -> InkMesh) -> &InkMesh {
+    self.mesh.get_or_init(|| make(&self.points))
+  }
+  
   pub fn length(&self) -> f32 {
-    self.start.distance(&self.end)
+    self.points.windows(2)
+      .map(|pair| pair[0].point.distance(&pair[1].point))
+      .sum()
+  }
+  
+  pub fn bounding_box(&self) -> Rect {
+    let mut bound = Rect {
+      x1: f32::INFINITY,
+      y1: f32::INFINITY,
+      x2: f32::NEG_INFINITY,
+      y2: f32::NEG_INFINITY
+    };
+    
+    for point in self.points.iter() {
+      bound.x1 = bound.x1.min(point.point.x);
+      bound.y1 = bound.y1.min(point.point.y);
+      bound.x2 = bound.x2.max(point.point.x);
+      bound.y2 = bound.y2.max(point.point.y);
+    }
+    
+    bound
+  }
+}
+
+// Pressure used for pens which never report the pressure axis
+pub const DEFAULT_PRESSURE: f32 = 0.4;
+
+#[derive(Clone)]
+pub struct InkPoint {
+  pub point: Point,
+  // SDL event timestamp in nanoseconds
+  pub timestamp: u64,
+  // 0.0 to 1.0, as reported by SDL
+  pub pressure: f32,
+  // .0 => horizontal tilt in degrees
+  // .1 => vertical tilt in degrees
+  // None if the pen does not report tilt
+  pub tilt: Option<(f32, f32)>
+}
+
+impl InkPoint {
+  // Point the fraction t of the way to other
+  pub fn lerp(&self, other: &InkPoint, t: f32) -> InkPoint {
+    InkPoint {
+      point: Point {
+        x: self.point.x + (other.point.x - self.point.x) * t,
+        y: self.point.y + (other.point.y - self.point.y) * t
+      },
+      timestamp: self.timestamp + (other.timestamp.saturating_sub(self.timestamp) as f64 * t as f64) as u64,
+      pressure: self.pressure + (other.pressure - self.pressure) * t,
+      tilt: self.tilt
+    }
   }
 }
 
@@ -36,9 +119,91 @@ impl Point {
   pub fn distance(&self, other: &Self) -> f32 {
     f32::sqrt(f32::powf(self.x - other.x, 2.0) + f32::powf(self.y - other.y, 2.0))
   }
+  
+  // Distance to the closest point on the segment from start to end
+  pub fn distance_to_segment(&self, start: &Point, end: &Point) -> f32 {
+    let dx = end.x - start.x;
+    let dy = end.y - start.y;
+    let length_squared = dx * dx + dy * dy;
+    if length_squared == 0.0 {
+      return self.distance(start);
+    }
+    
+    let t = (((self.x - start.x) * dx + (self.y - start.y) * dy) / length_squared).clamp(0.0, 1.0);
+    self.distance(&Point {
+      x: start.x + dx * t,
+      y: start.y + dy * t
+    })
+  }
 }
 
-#[derive(Clone)]
+// Part of the segment from start to end within radius of the segment
+// from near_start to near_end, as fractions along it. The area within
+// radius is a capsule, which is convex, so the part is a single span
+pub fn span_near_segment(start: &Point, end: &Point, near_start: &Point, near_end: &Point, radius: f32) -> Option<(f32, f32)> {
+  let dx = end.x - start.x;
+  let dy = end.y - start.y;
+  let length_squared = dx * dx + dy * dy;
+  let mut spans = Vec::new();
+  
+  // Round ends of the capsule
+  for center in [near_start, near_end] {
+    let ox = start.x - center.x;
+    let oy = start.y - center.y;
+    let c = ox * ox + oy * oy - radius * radius;
+    if length_squared == 0.0 {
+      if c <= 0.0 {
+        spans.push((0.0, 1.0));
+      }
+      continue;
+    }
+    
+    let b = dx * ox + dy * oy;
+    let discriminant = b * b - length_squared * c;
+    if discriminant >= 0.0 {
+      let root = discriminant.sqrt();
+      spans.push(((-b - root) / length_squared, (-b + root) / length_squared));
+    }
+  }
+  
+  // Band between the ends, in coordinates along and across the other segment
+  let ex = near_end.x - near_start.x;
+  let ey = near_end.y - near_start.y;
+  let near_length = f32::sqrt(ex * ex + ey * ey);
+  if near_length > 0.0 {
+    let ux = ex / near_length;
+    let uy = ey / near_length;
+    let ox = start.x - near_start.x;
+    let oy = start.y - near_start.y;
+    let mut t_enter = 0.0f32;
+    let mut t_exit = 1.0f32;
+    // Each as (value at start, change from start to end, lowest, highest)
+    for (value, change, low, high) in [
+      (ox * ux + oy * uy, dx * ux + dy * uy, 0.0, near_length),
+      (oy * ux - ox * uy, dy * ux - dx * uy, -radius, radius)
+    ] {
+      if change == 0.0 {
+        if value < low || value > high {
+          t_exit = -1.0;
+        }
+        continue;
+      }
+      
+      let a = (low - value) / change;
+      let b = (high - value) / change;
+      t_enter = t_enter.max(a.min(b));
+      t_exit = t_exit.min(a.max(b));
+    }
+    spans.push((t_enter, t_exit));
+  }
+  
+  spans.into_iter()
+    .map(|(t_enter, t_exit)| (t_enter.max(0.0), t_exit.min(1.0)))
+    .filter(|(t_enter, t_exit)| t_enter <= t_exit)
+    .reduce(|(enter1, exit1), (enter2, exit2)| (enter1.min(enter2), exit1.max(exit2)))
+}
+
+#[derive(Clone, Default)]
 pub struct Rect {
   pub x1: f32,
   pub y1: f32,
@@ -56,6 +221,53 @@ impl Rect {
     point.x >= x1 && point.x <= x2 &&
     point.y >= y1 && point.y <= y2
   }
+  
+  // Clips the segment to the part inside the rect (Liang-Barsky),
+  // None if the segment is entirely outside
+  pub fn clip_segment(&self, start: &Point, end: &Point) -> Option<(Point, Point)> {
+    let x1 = f32::min(self.x1, self.x2);
+    let y1 = f32::min(self.y1, self.y2);
+    let x2 = f32::max(self.x1, self.x2);
+    let y2 = f32::max(self.y1, self.y2);
+    
+    let dx = end.x - start.x;
+    let dy = end.y - start.y;
+    let mut t_enter = 0.0f32;
+    let mut t_exit = 1.0f32;
+    
+    // Each edge as (p, q) where the segment is inside that edge when p * t <= q
+    for (p, q) in [
+      (-dx, start.x - x1),
+      (dx, x2 - start.x),
+      (-dy, start.y - y1),
+      (dy, y2 - start.y)
+    ] {
+      if p == 0.0 {
+        // Parallel to the edge
+        if q < 0.0 {
+          return None;
+        }
+        continue;
+      }
+      
+      let t = q / p;
+      if p < 0.0 {
+        t_enter = t_enter.max(t);
+      } else {
+        t_exit = t_exit.min(t);
+      }
+      
+      if t_enter > t_exit {
+        return None;
+      }
+    }
+    
+    let at = |t: f32| Point {
+      x: (start.x + dx * t).clamp(x1, x2),
+      y: (start.y + dy * t).clamp(y1, y2)
+    };
+    Some((at(t_enter), at(t_exit)))
+  }
 }
 
 impl Into<sdl3::rect::Rect> for Rect {
@@ -89,3 +301,102 @@ impl Into<FRect> for Rect {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  fn rect() -> Rect {
+    Rect { x1: 0.0, y1: 0.0, x2: 100.0, y2: 50.0 }
+  }
+  
+  fn assert_point(point: &Point, x: f32, y: f32) {
+    assert!((point.x - x).abs() < 1e-4 && (point.y - y).abs() < 1e-4, "expected ({x}, {y}), got {point}");
+  }
+  
+  #[test]
+  fn clip_segment_keeps_segment_inside() {
+    let (start, end) = rect().clip_segment(&Point { x: 10.0, y: 10.0 }, &Point { x: 90.0, y: 40.0 }).unwrap();
+    assert_point(&start, 10.0, 10.0);
+    assert_point(&end, 90.0, 40.0);
+  }
+  
+  #[test]
+  fn clip_segment_cuts_at_the_edge_it_leaves_through() {
+    let (start, end) = rect().clip_segment(&Point { x: 50.0, y: 25.0 }, &Point { x: 150.0, y: 25.0 }).unwrap();
+    assert_point(&start, 50.0, 25.0);
+    assert_point(&end, 100.0, 25.0);
+  }
+  
+  #[test]
+  fn clip_segment_cuts_both_ends_when_crossing() {
+    let (start, end) = rect().clip_segment(&Point { x: -50.0, y: -25.0 }, &Point { x: 150.0, y: 75.0 }).unwrap();
+    assert_point(&start, 0.0, 0.0);
+    assert_point(&end, 100.0, 50.0);
+  }
+  
+  #[test]
+  fn clip_segment_drops_segment_outside() {
+    assert!(rect().clip_segment(&Point { x: 110.0, y: 10.0 }, &Point { x: 150.0, y: 40.0 }).is_none());
+    // Parallel to an edge, outside of it
+    assert!(rect().clip_segment(&Point { x: 10.0, y: -5.0 }, &Point { x: 90.0, y: -5.0 }).is_none());
+    // Would cross the corner region without entering
+    assert!(rect().clip_segment(&Point { x: 90.0, y: -20.0 }, &Point { x: 120.0, y: 10.0 }).is_none());
+  }
+  
+  #[test]
+  fn stroke_mesh_is_made_again_only_after_change() {
+    let ink_point = |x: f32| InkPoint { point: Point { x, y: 0.0 }, timestamp: 0, pressure: 0.5, tilt: None };
+    let mut made = 0;
+    let mut make = |_: &[InkPoint]| {
+      made += 1;
+      InkMesh::new(sdl3::pixels::Color::BLACK.into())
+    };
+    
+    let mut stroke = Stroke::new(PointerId::pen(0), ink_point(0.0));
+    stroke.mesh(&mut make);
+    stroke.mesh(&mut make);
+    // Copies in the history share the mesh
+    let copy = stroke.clone();
+    copy.mesh(&mut make);
+    stroke.push(ink_point(10.0));
+    stroke.mesh(&mut make);
+    stroke.last_point_mut().pressure = 0.7;
+    stroke.mesh(&mut make);
+    copy.mesh(&mut make);
+    assert_eq!(made, 3);
+  }
+  
+  #[test]
+  fn span_near_segment_is_where_it_crosses() {
+    let start = Point { x: 0.0, y: 0.0 };
+    let end = Point { x: 100.0, y: 0.0 };
+    let (t_enter, t_exit) = span_near_segment(&start, &end, &Point { x: 50.0, y: -40.0 }, &Point { x: 50.0, y: 40.0 }, 5.0).unwrap();
+    assert!((t_enter - 0.45).abs() < 1e-4 && (t_exit - 0.55).abs() < 1e-4, "{t_enter} {t_exit}");
+  }
+  
+  #[test]
+  fn span_near_segment_includes_round_ends() {
+    let start = Point { x: 0.0, y: 0.0 };
+    let end = Point { x: 100.0, y: 0.0 };
+    // Other segment ends 3 above the line, so the line passes 3 from its end
+    let (t_enter, t_exit) = span_near_segment(&start, &end, &Point { x: 50.0, y: -40.0 }, &Point { x: 50.0, y: -3.0 }, 5.0).unwrap();
+    assert!((t_enter - 0.46).abs() < 1e-4 && (t_exit - 0.54).abs() < 1e-4, "{t_enter} {t_exit}");
+    assert!(span_near_segment(&start, &end, &Point { x: 50.0, y: -40.0 }, &Point { x: 50.0, y: -6.0 }, 5.0).is_none());
+  }
+  
+  #[test]
+  fn span_near_segment_is_clamped_to_the_segment() {
+    let start = Point { x: 0.0, y: 0.0 };
+    let end = Point { x: 10.0, y: 0.0 };
+    assert_eq!(span_near_segment(&start, &end, &Point { x: -20.0, y: 1.0 }, &Point { x: 20.0, y: 1.0 }, 5.0), Some((0.0, 1.0)));
+    assert_eq!(span_near_segment(&start, &start, &Point { x: 3.0, y: 0.0 }, &Point { x: 3.0, y: 0.0 }, 5.0), Some((0.0, 1.0)));
+  }
+  
+  #[test]
+  fn clip_segment_works_with_flipped_rect() {
+    let flipped = Rect { x1: 100.0, y1: 50.0, x2: 0.0, y2: 0.0 };
+    let (_, end) = flipped.clip_segment(&Point { x: 50.0, y: 25.0 }, &Point { x: 50.0, y: 80.0 }).unwrap();
+    assert_point(&end, 50.0, 50.0);
+  }
+}
@@ -0,0 +1,85 @@
+use sdl3::{keyboard::{Keycode, Mod, Scancode}, sys::keycode::SDL_KMOD_NONE};
+use serde::Deserialize;
+
+use crate::keys::{self, Modifier};
+
+// Key with the modifiers held for it in the writer window, written
+// like "ctrl+z" or "ctrl+shift+return", names are those of keys.rs
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(try_from = "String")]
+pub struct Shortcut {
+  keycode: Keycode,
+  modifiers: Vec<Modifier>
+}
+
+impl Shortcut {
+  // Modifiers must match exactly, so ctrl+z does not fire on ctrl+shift+z.
+  // SDL applies shift and Caps Lock to the keycode of key events, so the
+  // key is looked up from the scancode as if no modifier was held
+  pub fn matches(&self, scancode: Scancode, keymod: Mod) -> bool {
+    Keycode::from_scancode(scancode, SDL_KMOD_NONE, true) == Some(self.keycode) &&
+      Modifier::ALL.iter().all(|modifier| self.modifiers.contains(modifier) == keymod.intersects(modifier.keymod()))
+  }
+}
+
+impl TryFrom<String> for Shortcut {
+  type Error = String;
+  
+  fn try_from(value: String) -> Result<Self, Self::Error> {
+    let mut names: Vec<&str> = value.split('+').map(str::trim).collect();
+    let key = names.pop().filter(|key| !key.is_empty())
+      .ok_or_else(|| format!("missing key in '{value}'"))?;
+    let (_, keycode) = keys::parse_key(key)
+      .ok_or_else(|| format!("unknown key '{key}' in '{value}'"))?;
+    
+    let modifiers = names.into_iter()
+      .map(|name| Modifier::parse(name).ok_or_else(|| format!("unknown modifier '{name}' in '{value}'")))
+      .collect::<Result<Vec<_>, _>>()?;
+    
+    Ok(Self {
+      keycode,
+      modifiers
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  
+  fn parse(value: &str) -> Result<Shortcut, String> {
+    Shortcut::try_from(value.to_string())
+  }
+  
+  #[test]
+  fn modifiers_are_parsed_in_any_case() {
+    let shortcut = parse("Control+SHIFT+z").unwrap();
+    assert_eq!(shortcut.keycode, Keycode::Z);
+    assert_eq!(shortcut.modifiers, vec![Modifier::Ctrl, Modifier::Shift]);
+    assert_eq!(parse("alt + z").unwrap(), Shortcut { keycode: Keycode::Z, modifiers: vec![Modifier::Alt] });
+    assert_eq!(parse("Super+Esc").unwrap(), Shortcut { keycode: Keycode::Escape, modifiers: vec![Modifier::Super] });
+    assert_eq!(parse("ctrl+enter").unwrap().keycode, Keycode::Return);
+  }
+  
+  #[test]
+  fn mistakes_are_rejected() {
+    assert_eq!(parse("ctrl+").unwrap_err(), "missing key in 'ctrl+'");
+    assert_eq!(parse("hyper+z").unwrap_err(), "unknown modifier 'hyper' in 'hyper+z'");
+    assert_eq!(parse("ctrl+nokey").unwrap_err(), "unknown key 'nokey' in 'ctrl+nokey'");
+  }
+  
+  #[test]
+  fn matches_held_modifiers_exactly() {
+    let redo = parse("ctrl+shift+z").unwrap();
+    assert!(redo.matches(Scancode::Z, Mod::LCTRLMOD | Mod::RSHIFTMOD));
+    assert!(!redo.matches(Scancode::Z, Mod::LCTRLMOD));
+    assert!(!redo.matches(Scancode::Y, Mod::LCTRLMOD | Mod::LSHIFTMOD));
+    
+    // Caps Lock is neither a modifier of the shortcut nor changes its key
+    let undo = parse("ctrl+z").unwrap();
+    assert!(undo.matches(Scancode::Z, Mod::LCTRLMOD | Mod::CAPSMOD));
+    assert!(!undo.matches(Scancode::Z, Mod::LCTRLMOD | Mod::LALTMOD));
+    assert!(!undo.matches(Scancode::Z, Mod::LCTRLMOD | Mod::LGUIMOD));
+    assert!(parse("win+e").unwrap().matches(Scancode::E, Mod::RGUIMOD));
+  }
+}
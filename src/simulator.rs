@@ -1,15 +1,18 @@
-use std::{sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}, thread};
+use std::{collections::VecDeque, sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}, thread};
 
 use rdev::{EventType, Key};
 use x11rb::{connection::Connection, protocol::xproto::{ChangeWindowAttributesAux, ConnectionExt, EventMask}};
 
 pub static DO_SHUTDOWN: AtomicBool = AtomicBool::new(false);
-static SIMULATE: Mutex<Option<SimulateAction>> = Mutex::new(None);
+// Actions in the order they were asked for, several can come in one frame
+static SIMULATE: Mutex<VecDeque<SimulateAction>> = Mutex::new(VecDeque::new());
 
 enum SimulateAction {
   Enter,
   Space,
   DelWord,
+  // Pressed in order and released in reverse, e.g. Ctrl+Z
+  Keys(Vec<Key>),
   String(String)
 }
 
@@ -43,16 +46,21 @@ pub fn main() {
   while DO_SHUTDOWN.load(Ordering::Relaxed) == false {
     thread::park();
     
-    let simulate_string = SIMULATE.lock().unwrap().take();
+    let actions: Vec<SimulateAction> = SIMULATE.lock().unwrap().drain(..).collect();
+    if actions.is_empty() {
+      continue;
+    }
+    
+    // Focus is switched back once for all of them, a second
+    // switch would go back to the writer
+    rdev::simulate(&EventType::KeyPress(Key::Alt)).unwrap();
+    rdev::simulate(&EventType::KeyPress(Key::Tab)).unwrap();
+    sdl3::timer::delay(50);
+    rdev::simulate(&EventType::KeyRelease(Key::Tab)).unwrap();
+    rdev::simulate(&EventType::KeyRelease(Key::Alt)).unwrap();
+    sdl3::timer::delay(50);
     
-    if let Some(action) = simulate_string {
-      rdev::simulate(&EventType::KeyPress(Key::Alt)).unwrap();
-      rdev::simulate(&EventType::KeyPress(Key::Tab)).unwrap();
-      sdl3::timer::delay(50);
-      rdev::simulate(&EventType::KeyRelease(Key::Tab)).unwrap();
-      rdev::simulate(&EventType::KeyRelease(Key::Alt)).unwrap();
-      sdl3::timer::delay(50);
-      
+    for action in actions {
       match action {
         SimulateAction::DelWord => {
           rdev::simulate(&EventType::KeyPress(Key::ControlLeft)).unwrap();
@@ -71,12 +79,23 @@ pub fn main() {
           sdl3::timer::delay(5);
           rdev::simulate(&EventType::KeyRelease(Key::Space)).unwrap();
         }
+        SimulateAction::Keys(keys) => {
+          for key in keys.iter() {
+            rdev::simulate(&EventType::KeyPress(*key)).unwrap();
+          }
+          sdl3::timer::delay(5);
+          for key in keys.iter().rev() {
+            rdev::simulate(&EventType::KeyRelease(*key)).unwrap();
+          }
+        }
         SimulateAction::String(text) => {
           log::info!("Request to simulate: {text} received");
           
           for chr in text.chars() {
             let mut keys = match chr.to_ascii_uppercase() {
               ' ' => [None, Some(Key::Space)],
+              // Recognized text has a newline between lines
+              '\n' => [None, Some(Key::Return)],
               '0' => [None, Some(Key::Num0)],
               '1' => [None, Some(Key::Num1)],
               '2' => [None, Some(Key::Num2)],
@@ -113,6 +132,40 @@ pub fn main() {
               'X' => [Some(Key::ShiftLeft), Some(Key::KeyX)],
               'Y' => [Some(Key::ShiftLeft), Some(Key::KeyY)],
               'Z' => [Some(Key::ShiftLeft), Some(Key::KeyZ)],
+              // Punctuation as on a US layout, for snippets of code
+              '\t' => [None, Some(Key::Tab)],
+              '`' => [None, Some(Key::BackQuote)],
+              '~' => [Some(Key::ShiftLeft), Some(Key::BackQuote)],
+              '!' => [Some(Key::ShiftLeft), Some(Key::Num1)],
+              '@' => [Some(Key::ShiftLeft), Some(Key::Num2)],
+              '#' => [Some(Key::ShiftLeft), Some(Key::Num3)],
+              '$' => [Some(Key::ShiftLeft), Some(Key::Num4)],
+              '%' => [Some(Key::ShiftLeft), Some(Key::Num5)],
+              '^' => [Some(Key::ShiftLeft), Some(Key::Num6)],
+              '&' => [Some(Key::ShiftLeft), Some(Key::Num7)],
+              '*' => [Some(Key::ShiftLeft), Some(Key::Num8)],
+              '(' => [Some(Key::ShiftLeft), Some(Key::Num9)],
+              ')' => [Some(Key::ShiftLeft), Some(Key::Num0)],
+              '-' => [None, Some(Key::Minus)],
+              '_' => [Some(Key::ShiftLeft), Some(Key::Minus)],
+              '=' => [None, Some(Key::Equal)],
+              '+' => [Some(Key::ShiftLeft), Some(Key::Equal)],
+              '[' => [None, Some(Key::LeftBracket)],
+              '{' => [Some(Key::ShiftLeft), Some(Key::LeftBracket)],
+              ']' => [None, Some(Key::RightBracket)],
+              '}' => [Some(Key::ShiftLeft), Some(Key::RightBracket)],
+              '\\' => [None, Some(Key::BackSlash)],
+              '|' => [Some(Key::ShiftLeft), Some(Key::BackSlash)],
+              ';' => [None, Some(Key::SemiColon)],
+              ':' => [Some(Key::ShiftLeft), Some(Key::SemiColon)],
+              '\'' => [None, Some(Key::Quote)],
+              '"' => [Some(Key::ShiftLeft), Some(Key::Quote)],
+              ',' => [None, Some(Key::Comma)],
+              '<' => [Some(Key::ShiftLeft), Some(Key::Comma)],
+              '.' => [None, Some(Key::Dot)],
+              '>' => [Some(Key::ShiftLeft), Some(Key::Dot)],
+              '/' => [None, Some(Key::Slash)],
+              '?' => [Some(Key::ShiftLeft), Some(Key::Slash)],
               _ => continue
             };
             
@@ -139,18 +192,22 @@ pub fn main() {
 }
 
 pub fn simulate(text: String) {
-  *SIMULATE.lock().unwrap() = Some(SimulateAction::String(text));
+  SIMULATE.lock().unwrap().push_back(SimulateAction::String(text));
 }
 
 pub fn simulate_delword() {
-  *SIMULATE.lock().unwrap() = Some(SimulateAction::DelWord);
+  SIMULATE.lock().unwrap().push_back(SimulateAction::DelWord);
 }
 
 pub fn simulate_enter() {
-  *SIMULATE.lock().unwrap() = Some(SimulateAction::Enter);
+  SIMULATE.lock().unwrap().push_back(SimulateAction::Enter);
 }
 
 pub fn simulate_space() {
-  *SIMULATE.lock().unwrap() = Some(SimulateAction::Space);
+  SIMULATE.lock().unwrap().push_back(SimulateAction::Space);
+}
+
+pub fn simulate_keys(keys: Vec<Key>) {
+  SIMULATE.lock().unwrap().push_back(SimulateAction::Keys(keys));
 }
 
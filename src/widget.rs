@@ -0,0 +1,115 @@
+use std::{cell::RefCell, rc::Rc};
+
+use taffy::{AvailableSpace, NodeId, Size, Style, TaffyTree};
+
+use crate::{input::{PointerEvent, PointerId}, shapes::Rect};
+
+// Something placed by the layout, drawn and given pointer events
+pub trait Widget {
+  fn set_bound(&mut self, bound: Rect);
+  
+  fn draw(&self);
+  
+  // Called at the start of every frame, before the events
+  fn begin_frame(&mut self) {}
+  
+  // Every widget gets every event, it checks its own bound
+  fn pointer_event(&mut self, _event: &PointerEvent) {}
+  
+  // Lets go of the pointer without acting on it, e.g. for palms
+  fn cancel(&mut self, _pointer: PointerId) {}
+}
+
+// Owns the layout and the widgets in it, widgets are drawn and
+// given events in the order they are added
+pub struct WidgetTree {
+  layout: TaffyTree<Rc<RefCell<dyn Widget>>>,
+  root: NodeId,
+  widgets: Vec<Rc<RefCell<dyn Widget>>>
+}
+
+impl WidgetTree {
+  pub fn new(root_style: Style) -> Self {
+    let mut layout = TaffyTree::new();
+    let root = layout.new_leaf(root_style).unwrap();
+    
+    Self {
+      layout,
+      root,
+      widgets: Vec::new()
+    }
+  }
+  
+  pub fn root(&self) -> NodeId {
+    self.root
+  }
+  
+  // Node only arranging its children
+  pub fn add_container(&mut self, parent: NodeId, style: Style) -> NodeId {
+    let node = self.layout.new_leaf(style).unwrap();
+    self.layout.add_child(parent, node).unwrap();
+    node
+  }
+  
+  // Handle is kept by the caller to read and change the widget
+  pub fn add<W: Widget + 'static>(&mut self, parent: NodeId, style: Style, widget: W) -> Rc<RefCell<W>> {
+    let widget = Rc::new(RefCell::new(widget));
+    let node = self.layout.new_leaf_with_context(style, widget.clone()).unwrap();
+    self.layout.add_child(parent, node).unwrap();
+    self.widgets.push(widget.clone());
+    widget
+  }
+  
+  pub fn compute_layout(&mut self, width: f32, height: f32) {
+    self.layout.compute_layout(
+      self.root,
+      Size {
+        width: AvailableSpace::Definite(width),
+        height: AvailableSpace::Definite(height)
+      }
+    ).unwrap();
+    
+    self.place(self.root, 0.0, 0.0);
+  }
+  
+  // Layout locations are relative to the parent, so the offsets add up going down
+  fn place(&self, node: NodeId, parent_x: f32, parent_y: f32) {
+    let layout = self.layout.layout(node).unwrap();
+    if let Some(widget) = self.layout.get_node_context(node) {
+      widget.borrow_mut().set_bound(Rect {
+        x1: parent_x + layout.content_box_x(),
+        y1: parent_y + layout.content_box_y(),
+        x2: parent_x + layout.content_box_x() + layout.content_box_width(),
+        y2: parent_y + layout.content_box_y() + layout.content_box_height()
+      });
+    }
+    
+    for child in self.layout.children(node).unwrap() {
+      self.place(child, parent_x + layout.location.x, parent_y + layout.location.y);
+    }
+  }
+  
+  pub fn draw(&self) {
+    for widget in &self.widgets {
+      widget.borrow().draw();
+    }
+  }
+  
+  pub fn begin_frame(&self) {
+    for widget in &self.widgets {
+      widget.borrow_mut().begin_frame();
+    }
+  }
+  
+  pub fn pointer_event(&self, event: &PointerEvent) {
+    for widget in &self.widgets {
+      widget.borrow_mut().pointer_event(event);
+    }
+  }
+  
+  pub fn cancel(&self, pointer: PointerId) {
+    for widget in &self.widgets {
+      widget.borrow_mut().cancel(pointer);
+    }
+  }
+}
@@ -58,14 +58,6 @@ impl Window {
     &self.canvas
   }
   
-  pub fn get_canvas_width(&self) -> u32 {
-    self.canvas.borrow().logical_size().0
-  }
-  
-  pub fn get_canvas_height(&self) -> u32 {
-    self.canvas.borrow().logical_size().1
-  }
-  
   pub fn get_width(&self) -> u32 {
     self.window.size().0
   }
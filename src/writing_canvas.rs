@@ -1,8 +1,33 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::{HashMap, VecDeque}, rc::Rc, time::Duration};
 
-use sdl3::{pixels::{Color, PixelFormat}, render::Canvas, video::Window};
+use sdl3::{pen::PenAxis, pixels::{Color, PixelFormat}, render::{BlendMode, Canvas, FRect}, video::Window};
 
-use crate::shapes::{Rect, Stroke, Point};
+use crate::{gesture::{self, EditGesture}, ink::{self, InkMesh}, input::{PointerEvent, PointerId}, shapes::{self, DEFAULT_PRESSURE, InkPoint, Point, Rect, Stroke}, widget::Widget};
+
+// Latest axis values reported for a pen
+#[derive(Clone, Default)]
+struct PenAxes {
+  pressure: Option<f32>,
+  tilt_x: Option<f32>,
+  tilt_y: Option<f32>
+}
+
+impl PenAxes {
+  fn make_point(&self, x: f32, y: f32, timestamp: u64) -> InkPoint {
+    let tilt = if self.tilt_x.is_some() || self.tilt_y.is_some() {
+      Some((self.tilt_x.unwrap_or(0.0), self.tilt_y.unwrap_or(0.0)))
+    } else {
+      None
+    };
+    
+    InkPoint {
+      point: Point { x, y },
+      timestamp,
+      pressure: self.pressure.unwrap_or(DEFAULT_PRESSURE),
+      tilt
+    }
+  }
+}
 
 pub struct WritingCanvas {
   bound: Rect,
@@ -10,23 +35,75 @@ pub struct WritingCanvas {
   stroke_distance_threshold: f32,
   // .0 = which pen
   // .1 = whether the pen is out or in the bound
-  current_pen: Option<(u32, bool)>,
+  // .2 = whether the pen is erasing instead of inking
+  current_pen: Option<(PointerId, bool, bool)>,
+  // Eraser used for pens without eraser end
+  eraser_toggled: bool,
+  // false removes only the parts of strokes under the eraser
+  erase_whole_strokes: bool,
+  eraser_radius: f32,
+  // Where the current pen was last seen, may be outside of the bound
+  last_pen_point: Option<Point>,
+  // Snapshot taken at eraser down, saved to history once something is erased
+  pending_history: Option<Vec<Stroke>>,
+  // Redo history from before the pen went down, put back if what
+  // the pen did is thrown away
+  pending_redo: Option<Vec<Vec<Stroke>>>,
+  // Edit gesture drawn but not acted on yet
+  pending_gesture: Option<EditGesture>,
+  // Pen finished changing the ink and nothing has asked about it yet
+  has_finished_stroke: bool,
+  // Flicks are easily drawn by accident, e.g. as a dash
+  flick_gestures: bool,
   canvas: Rc<RefCell<Canvas<Window>>>,
-  all_strokes: Vec<Stroke>
+  all_strokes: Vec<Stroke>,
+  // Snapshots of all_strokes before each edit, newest at the back
+  undo_history: VecDeque<Vec<Stroke>>,
+  redo_history: Vec<Vec<Stroke>>,
+  history_limit: usize,
+  pen_axes: HashMap<PointerId, PenAxes>,
+  // Ink width at zero and full pressure
+  min_ink_width: f32,
+  max_ink_width: f32
 }
 
 impl WritingCanvas {
-  pub fn new(bound: Rect, canvas: Rc<RefCell<Canvas<Window>>>) -> Self {
+  pub fn new(canvas: Rc<RefCell<Canvas<Window>>>) -> Self {
     Self {
-      bound,
+      bound: Rect::default(),
       canvas,
       update_count: 0,
       current_pen: None,
+      eraser_toggled: false,
+      erase_whole_strokes: false,
+      eraser_radius: 8.0,
+      last_pen_point: None,
+      pending_history: None,
+      pending_redo: None,
+      pending_gesture: None,
+      has_finished_stroke: false,
+      flick_gestures: false,
       stroke_distance_threshold: 2.0,
-      all_strokes: Vec::new()
+      all_strokes: Vec::new(),
+      undo_history: VecDeque::new(),
+      redo_history: Vec::new(),
+      history_limit: 100,
+      pen_axes: HashMap::new(),
+      min_ink_width: 1.0,
+      max_ink_width: 6.0
     }
   }
   
+  pub fn with_erase_whole_strokes(mut self, erase_whole_strokes: bool) -> Self {
+    self.erase_whole_strokes = erase_whole_strokes;
+    self
+  }
+  
+  pub fn with_flick_gestures(mut self, flick_gestures: bool) -> Self {
+    self.flick_gestures = flick_gestures;
+    self
+  }
+  
   pub fn with_pixels<R, F: FnOnce(&[u8], u32, u32, u32, PixelFormat) -> R>(&self, func: F) -> R {
     let canvas = self.canvas.borrow();
     let surface = canvas
@@ -54,7 +131,19 @@ impl WritingCanvas {
     self.update_count
   }
   
-  pub fn pen_down(&mut self, x: f32, y: f32, pen: u32) {
+  pub fn is_empty(&self) -> bool {
+    self.all_strokes.is_empty()
+  }
+  
+  pub fn toggle_eraser(&mut self) {
+    self.eraser_toggled = !self.eraser_toggled;
+  }
+  
+  pub fn is_eraser_toggled(&self) -> bool {
+    self.eraser_toggled
+  }
+  
+  fn pen_down(&mut self, x: f32, y: f32, pen: PointerId, timestamp: u64, eraser: bool) {
     if !self.bound.contains(&Point { x, y }) {
       return;
     }
@@ -66,36 +155,181 @@ impl WritingCanvas {
       return;
     }
     
-    self.current_pen = Some((pen, true));
-    self.all_strokes.push(Stroke {
-      start: Point { x, y },
-      end: Point { x, y }
-    });
+    self.pending_redo = None;
+    if eraser || self.eraser_toggled {
+      self.current_pen = Some((pen, true, true));
+      self.pending_history = Some(self.all_strokes.clone());
+      self.last_pen_point = Some(Point { x, y });
+      self.erase_along(&Point { x, y }, &Point { x, y });
+      return;
+    }
+    
+    self.push_pen_history(self.all_strokes.clone());
+    self.current_pen = Some((pen, true, false));
+    self.last_pen_point = Some(Point { x, y });
+    let point = self.make_point(pen, x, y, timestamp);
+    self.all_strokes.push(Stroke::new(pen, point));
     self.update_count += 1;
   }
   
-  pub fn pen_up(&mut self, x: f32, y: f32, pen: u32) {
-    if let Some(current_pen) = self.current_pen {
-      if current_pen.0 != pen {
-        return;
+  fn pen_up(&mut self, x: f32, y: f32, pen: PointerId, timestamp: u64) {
+    let Some(current_pen) = self.current_pen else {
+      return;
+    };
+    
+    if current_pen.0 != pen {
+      return;
+    }
+    
+    self.move_pen(current_pen, x, y, timestamp);
+    self.current_pen = None;
+    self.last_pen_point = None;
+    // Eraser only changed something if its snapshot was saved
+    let has_edited = !current_pen.2 || self.pending_history.is_none();
+    self.pending_history = None;
+    
+    let is_gesture = !current_pen.2 && self.detect_gesture();
+    self.has_finished_stroke |= has_edited && !is_gesture;
+    self.pending_redo = None;
+  }
+  
+  // Checks whether the stroke just finished is a gesture
+  // rather than writing, and acts on it. True for edit gestures,
+  // which leave the ink as it was
+  fn detect_gesture(&mut self) -> bool {
+    let Some(latest_stroke) = self.all_strokes.last() else {
+      return false;
+    };
+    
+    if gesture::is_scratch_out(latest_stroke) {
+      let scribble = latest_stroke.clone();
+      let is_scratched = |stroke: &Stroke| gesture::is_scratched_by(stroke, &scribble);
+      // Only strokes from before the pen went down can be under it
+      if !self.undo_history.back().is_some_and(|strokes| strokes.iter().any(is_scratched)) {
+        // Nothing under it, must be writing after all
+        return false;
       }
+      
+      // Every stroke of it goes, also parts from when the pen left the
+      // canvas and came back, then erasing what it covers is the edit
+      self.revert_pen_edit();
+      self.save_history();
+      let count = self.all_strokes.len();
+      self.all_strokes.retain(|stroke| !is_scratched(stroke));
+      
+      log::info!("Scratched out {} strokes", count - self.all_strokes.len());
+      self.update_count += 1;
+      return false;
+    }
+    
+    let gesture = gesture::detect_edit_gesture(latest_stroke)
+      .filter(|gesture| self.flick_gestures || *gesture == EditGesture::Enter);
+    if let Some(gesture) = gesture {
+      log::info!("Edit gesture {gesture:?} drawn");
+      // Every stroke of it goes, also parts from when the pen left
+      // the canvas and came back, nothing for undo to do
+      self.revert_pen_edit();
+      self.pending_gesture = Some(gesture);
+      return true;
+    }
+    
+    false
+  }
+  
+  // Whether a stroke or erasing was finished since last asked
+  pub fn take_finished_stroke(&mut self) -> bool {
+    std::mem::take(&mut self.has_finished_stroke)
+  }
+  
+  pub fn take_gesture(&mut self) -> Option<EditGesture> {
+    self.pending_gesture.take()
+  }
+  
+  // Throws away what the pen did since it went down
+  fn cancel_pen(&mut self, pen: PointerId) {
+    let Some(current_pen) = self.current_pen else {
+      return;
+    };
+    
+    if current_pen.0 != pen {
+      return;
     }
     
+    // Eraser which has not erased anything yet has nothing to undo
+    let has_edited = !current_pen.2 || self.pending_history.is_none();
     self.current_pen = None;
-    if !self.bound.contains(&Point { x, y }) {
+    self.last_pen_point = None;
+    self.pending_history = None;
+    if has_edited {
+      self.revert_pen_edit();
+    }
+    self.pending_redo = None;
+  }
+  
+  // Puts the strokes and redo history back to how they were
+  // before the pen went down
+  fn revert_pen_edit(&mut self) {
+    let Some(strokes) = self.undo_history.pop_back() else {
+      return;
+    };
+    
+    self.all_strokes = strokes;
+    if let Some(redo_history) = self.pending_redo.take() {
+      self.redo_history = redo_history;
+    }
+    self.update_count += 1;
+  }
+  
+  pub fn pen_axis(&mut self, pen: PointerId, axis: PenAxis, value: f32) {
+    let axes = self.pen_axes.entry(pen).or_default();
+    match axis {
+      PenAxis::Pressure => axes.pressure = Some(value),
+      PenAxis::XTilt => axes.tilt_x = Some(value),
+      PenAxis::YTilt => axes.tilt_y = Some(value),
+      _ => return
+    }
+    
+    let Some(current_pen) = self.current_pen else {
+      return;
+    };
+    
+    if current_pen.0 != pen || !current_pen.1 || current_pen.2 {
       return;
     }
     
-    self.all_strokes.last_mut().unwrap().end = Point { x, y };
+    // Pressure usually changes without the pen moving, update
+    // the tip of current stroke so the width follows it
+    let axes = axes.clone();
+    let tip = self.all_strokes.last_mut().unwrap().last_point_mut();
+    *tip = axes.make_point(tip.point.x, tip.point.y, tip.timestamp);
     self.update_count += 1;
   }
   
-  pub fn set_bound(&mut self, rect: Rect) {
-    self.bound = rect;
+  fn make_point(&self, pen: PointerId, x: f32, y: f32, timestamp: u64) -> InkPoint {
+    self.pen_axes.get(&pen)
+      .cloned()
+      .unwrap_or_default()
+      .make_point(x, y, timestamp)
+  }
+  
+  fn ink_width(&self, pressure: f32) -> f32 {
+    self.min_ink_width + (self.max_ink_width - self.min_ink_width) * pressure.clamp(0.0, 1.0)
   }
   
-  pub fn pen_motion(&mut self, x: f32, y: f32, pen: u32) {
-    let Some(current_pen) = self.current_pen.as_mut() else {
+  // Moves the tip of current stroke to the point, the tip is kept
+  // as new point once it is far enough from the previous one
+  fn extend_stroke(&mut self, point: InkPoint) {
+    let latest_stroke = self.all_strokes.last_mut().unwrap();
+    let len = latest_stroke.points.len();
+    if len >= 2 && latest_stroke.points[len - 2].point.distance(&latest_stroke.points[len - 1].point) < self.stroke_distance_threshold {
+      *latest_stroke.last_point_mut() = point;
+    } else {
+      latest_stroke.push(point);
+    }
+  }
+  
+  fn pen_motion(&mut self, x: f32, y: f32, pen: PointerId, timestamp: u64) {
+    let Some(current_pen) = self.current_pen else {
       return;
     };
     
@@ -103,68 +337,378 @@ impl WritingCanvas {
       return;
     }
     
-    if !self.bound.contains(&Point { x, y }) {
-      // Pen went outside of the bound
-      // TODO: Clip it instead
-      current_pen.1 = false;
+    self.move_pen(current_pen, x, y, timestamp);
+  }
+  
+  fn move_pen(&mut self, current_pen: (PointerId, bool, bool), x: f32, y: f32, timestamp: u64) {
+    let (pen, was_in, erasing) = current_pen;
+    let current = Point { x, y };
+    let last = self.last_pen_point.replace(current.clone()).unwrap_or(current.clone());
+    
+    if erasing {
+      self.erase_along(&last, &current);
       return;
     }
     
-    if current_pen.1 == false {
-      // Pen was out of bound, start new stroke
-      current_pen.1 = true;
-      self.all_strokes.push(Stroke {
-        start: Point { x, y },
-        end: Point { x, y }
-      });
+    // Only the part of the movement inside the bound is inked,
+    // the stroke ends where pen leaves and new one starts where it comes back
+    let Some((entry, exit)) = self.bound.clip_segment(&last, &current) else {
+      self.current_pen = Some((pen, false, erasing));
+      return;
+    };
+    
+    if was_in == false {
+      let point = self.make_point(pen, entry.x, entry.y, timestamp);
+      self.all_strokes.push(Stroke::new(pen, point));
     }
     
-    let latest_stroke = self.all_strokes.last_mut().unwrap();
-    latest_stroke.end = Point { x, y };
+    let point = self.make_point(pen, exit.x, exit.y, timestamp);
+    self.extend_stroke(point);
+    self.current_pen = Some((pen, self.bound.contains(&current), erasing));
     self.update_count += 1;
-    
-    if latest_stroke.length() >= self.stroke_distance_threshold {
-      self.all_strokes.push(Stroke {
-        start: Point { x, y },
-        end: Point { x, y }
-      });
-    }
   }
   
+  // Clears the canvas, can be undone
   pub fn clear(&mut self) {
+    if !self.all_strokes.is_empty() {
+      self.save_history();
+    }
+    
+    self.current_pen = None;
+    self.all_strokes.clear();
+    // Recognition of what was there is no longer current
+    self.update_count += 1;
+  }
+  
+  // Clears the canvas and forgets the history
+  pub fn reset(&mut self) {
     self.current_pen = None;
     self.all_strokes.clear();
+    self.undo_history.clear();
+    self.redo_history.clear();
+    self.update_count += 1;
+  }
+  
+  pub fn undo(&mut self) -> bool {
+    let Some(strokes) = self.undo_history.pop_back() else {
+      return false;
+    };
+    
+    // Any stroke in progress belongs to the undone edit
+    self.current_pen = None;
+    self.redo_history.push(std::mem::replace(&mut self.all_strokes, strokes));
+    self.update_count += 1;
+    true
+  }
+  
+  pub fn redo(&mut self) -> bool {
+    let Some(strokes) = self.redo_history.pop() else {
+      return false;
+    };
+    
+    self.current_pen = None;
+    self.undo_history.push_back(std::mem::replace(&mut self.all_strokes, strokes));
+    self.update_count += 1;
+    true
+  }
+  
+  // Must be called before each edit to the strokes
+  fn save_history(&mut self) {
+    self.push_history(self.all_strokes.clone());
+  }
+  
+  // Redo history is kept aside until the pen is done, in case what it did is thrown away
+  fn push_pen_history(&mut self, snapshot: Vec<Stroke>) {
+    self.pending_redo = Some(std::mem::take(&mut self.redo_history));
+    self.push_history(snapshot);
+  }
+  
+  fn push_history(&mut self, snapshot: Vec<Stroke>) {
+    self.redo_history.clear();
+    self.undo_history.push_back(snapshot);
+    if self.undo_history.len() > self.history_limit {
+      self.undo_history.pop_front();
+    }
+  }
+  
+  // Removes ink under the eraser as it moves from start to end
+  fn erase_along(&mut self, start: &Point, end: &Point) {
+    let radius = self.eraser_radius;
+    let erase_whole_strokes = self.erase_whole_strokes;
+    // Whole segments are checked, as points of fast strokes
+    // are much further apart than the eraser is wide
+    let erased_span = |from: &InkPoint, to: &InkPoint| shapes::span_near_segment(&from.point, &to.point, start, end, radius);
+    
+    let mut changed = false;
+    let mut remaining = Vec::new();
+    for stroke in self.all_strokes.drain(..) {
+      let spans: Vec<Option<(f32, f32)>> = match stroke.points.as_slice() {
+        [point] => vec![erased_span(point, point)],
+        points => points.windows(2).map(|pair| erased_span(&pair[0], &pair[1])).collect()
+      };
+      if spans.iter().all(Option::is_none) {
+        remaining.push(stroke);
+        continue;
+      }
+      
+      changed = true;
+      if erase_whole_strokes || stroke.points.len() == 1 {
+        continue;
+      }
+      
+      // Parts of stroke left on both sides of the eraser become
+      // their own strokes, cut where the eraser goes over them
+      let mut part = if spans[0].is_some_and(|(t_enter, _)| t_enter == 0.0) {
+        Vec::new()
+      } else {
+        vec![stroke.points[0].clone()]
+      };
+      for (pair, span) in stroke.points.windows(2).zip(spans) {
+        let (from, to) = (&pair[0], &pair[1]);
+        let Some((t_enter, t_exit)) = span else {
+          part.push(to.clone());
+          continue;
+        };
+        
+        if t_enter > 0.0 {
+          part.push(from.lerp(to, t_enter));
+        }
+        if part.len() > 1 {
+          remaining.push(Stroke::from_points(stroke.pen, std::mem::take(&mut part)));
+        }
+        part.clear();
+        
+        if t_exit < 1.0 {
+          part.push(from.lerp(to, t_exit));
+          part.push(to.clone());
+        }
+      }
+      
+      if part.len() > 1 {
+        remaining.push(Stroke::from_points(stroke.pen, part));
+      }
+    }
+    self.all_strokes = remaining;
+    
+    if changed {
+      if let Some(snapshot) = self.pending_history.take() {
+        self.push_pen_history(snapshot);
+      }
+      self.update_count += 1;
+    }
+  }
+  
+  // Bar along the bottom of the canvas shrinking as the time runs
+  // out, drawn after the pixels are read so OCR does not see it
+  pub fn draw_countdown(&self, remaining: f32) {
+    let bar = Rect {
+      x1: self.bound.x1,
+      y1: self.bound.y2 - 6.0,
+      x2: self.bound.x1 + (self.bound.x2 - self.bound.x1) * remaining,
+      y2: self.bound.y2
+    };
+    
+    let mut canvas = self.canvas.borrow_mut();
+    canvas.set_draw_color(Color::RGB(0x33, 0x66, 0xCC));
+    let _ = canvas.fill_rect(Some(bar.into()))
+      .map_err(|e| log::warn!("error calling canvas.fill_rect {e}"));
+  }
+  
+  // Block sliding back and forth along the top of the canvas
+  pub fn draw_busy(&self, elapsed: Duration) {
+    let width = self.bound.x2 - self.bound.x1;
+    let block_width = width / 5.0;
+    // Goes across and back once every two seconds
+    let phase = (elapsed.as_secs_f32() % 2.0) / 2.0;
+    let position = 1.0 - (phase * 2.0 - 1.0).abs();
+    let x = self.bound.x1 + (width - block_width) * position;
+    let block = Rect {
+      x1: x,
+      y1: self.bound.y1,
+      x2: x + block_width,
+      y2: self.bound.y1 + 6.0
+    };
+    
+    let mut canvas = self.canvas.borrow_mut();
+    canvas.set_draw_color(Color::RGB(0xCC, 0x88, 0x33));
+    let _ = canvas.fill_rect(Some(block.into()))
+      .map_err(|e| log::warn!("error calling canvas.fill_rect {e}"));
+  }
+  
+  // Red frame around the canvas while recognition is failing
+  pub fn draw_error(&self) {
+    let mut canvas = self.canvas.borrow_mut();
+    canvas.set_draw_color(Color::RGB(0xCC, 0x33, 0x33));
+    for thickness in 0..3 {
+      let frame = Rect {
+        x1: self.bound.x1 + thickness as f32,
+        y1: self.bound.y1 + thickness as f32,
+        x2: self.bound.x2 - thickness as f32,
+        y2: self.bound.y2 - thickness as f32
+      };
+      let frame: FRect = frame.into();
+      let _ = canvas.draw_rect(frame)
+        .map_err(|e| log::warn!("error calling canvas.draw_rect {e}"));
+    }
+  }
+  
+  // Translucent boxes over words which were recognized with low confidence,
+  // the boxes are in pixels of the image given to the processing thread
+  pub fn draw_low_confidence<'a>(&self, boxes: impl Iterator<Item = &'a Rect>) {
+    let mut canvas = self.canvas.borrow_mut();
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0xFF, 0xAA, 0x00, 0x55));
+    for bound in boxes {
+      let highlight = Rect {
+        x1: self.bound.x1 + bound.x1,
+        y1: self.bound.y1 + bound.y1,
+        x2: self.bound.x1 + bound.x2,
+        y2: self.bound.y1 + bound.y2
+      };
+      let _ = canvas.fill_rect(Some(highlight.into()))
+        .map_err(|e| log::warn!("error calling canvas.fill_rect {e}"));
+    }
+    canvas.set_blend_mode(BlendMode::None);
+  }
+}
+
+impl Widget for WritingCanvas {
+  fn set_bound(&mut self, rect: Rect) {
+    self.bound = rect;
   }
   
-  pub fn draw(&self) {
+  fn draw(&self) {
     let mut canvas = self.canvas.borrow_mut();
     canvas.set_draw_color(Color::RGB(0x88, 0x88, 0x88));
     let _ = canvas.fill_rect(Some(self.bound.clone().into()))
       .map_err(|e| log::warn!("error calling canvas.fill_rect {e}"));
     
-    canvas.set_draw_color(Color::BLACK);
+    // Only strokes which changed since the last frame get a new mesh
+    let make_mesh = |points: &[InkPoint]| {
+      let line = ink::smooth(points)
+        .into_iter()
+        .map(|(point, pressure)| (point, self.ink_width(pressure)))
+        .collect::<Vec<_>>();
+      let joins = points.iter()
+        .map(|point| (point.point.clone(), self.ink_width(point.pressure)))
+        .collect::<Vec<_>>();
+      let mut mesh = InkMesh::new(Color::BLACK.into());
+      mesh.add_polyline(&line, &joins);
+      mesh
+    };
+    
+    // Keep the ink from spilling over the border
+    let clip: sdl3::rect::Rect = self.bound.clone().into();
+    canvas.set_clip_rect(clip);
+    canvas.set_blend_mode(BlendMode::Blend);
     for stroke in self.all_strokes.iter() {
-      [
-        (stroke.start.clone(), stroke.end.clone()),
-        (Point { x: stroke.start.x + 1.0, y: stroke.start.y       }, Point { x: stroke.end.x + 1.0, y: stroke.end.y       }),
-        (Point { x: stroke.start.x + 1.0, y: stroke.start.y + 1.0 }, Point { x: stroke.end.x + 1.0, y: stroke.end.y + 1.0 }),
-        (Point { x: stroke.start.x      , y: stroke.start.y + 1.0 }, Point { x: stroke.end.x      , y: stroke.end.y + 1.0 }),
-        (Point { x: stroke.start.x - 1.0, y: stroke.start.y       }, Point { x: stroke.end.x - 1.0, y: stroke.end.y       }),
-        (Point { x: stroke.start.x - 1.0, y: stroke.start.y - 1.0 }, Point { x: stroke.end.x - 1.0, y: stroke.end.y - 1.0 }),
-        (Point { x: stroke.start.x      , y: stroke.start.y - 1.0 }, Point { x: stroke.end.x      , y: stroke.end.y - 1.0 }),
-        
-        (Point { x: stroke.start.x + 1.0, y: stroke.start.y - 1.0 }, Point { x: stroke.end.x + 1.0, y: stroke.end.y - 1.0 }),
-        (Point { x: stroke.start.x - 1.0, y: stroke.start.y + 1.0 }, Point { x: stroke.end.x - 1.0, y: stroke.end.y + 1.0 }),
-      ].into_iter()
-        .for_each(|(start, end)| {
-          if !self.bound.contains(&start) || !self.bound.contains(&end) {
-            return;
-          }
-          
-          let _ = canvas.draw_line(start, end)
-            .map_err(|e| log::warn!("error calling canvas.draw_line: {e}"));
-        });
+      let mesh = stroke.mesh(make_mesh);
+      let _ = canvas.render_geometry(&mesh.vertices, None, mesh.indices.as_slice())
+        .map_err(|e| log::warn!("error calling canvas.render_geometry: {e}"));
+    }
+    canvas.set_blend_mode(BlendMode::None);
+    canvas.set_clip_rect(None::<sdl3::rect::Rect>);
+  }
+  
+  fn pointer_event(&mut self, event: &PointerEvent) {
+    match *event {
+      PointerEvent::Down { pointer, x, y, timestamp, eraser, .. } => self.pen_down(x, y, pointer, timestamp, eraser),
+      PointerEvent::Up { pointer, x, y, timestamp } => self.pen_up(x, y, pointer, timestamp),
+      PointerEvent::Motion { pointer, x, y, timestamp } => self.pen_motion(x, y, pointer, timestamp)
     }
   }
+  
+  fn cancel(&mut self, pointer: PointerId) {
+    self.cancel_pen(pointer);
+  }
 }
 
+#[cfg(test)]
+mod tests {
+  use std::sync::Mutex;
+  
+  use super::*;
+  
+  // SDL can only be used from one thread at a time
+  static SDL: Mutex<()> = Mutex::new(());
+  
+  // Canvas on SDL's dummy video driver, which needs no display
+  fn with_canvas(erase_whole_strokes: bool, test: impl FnOnce(&mut WritingCanvas)) {
+    let _lock = SDL.lock().unwrap_or_else(|e| e.into_inner());
+    sdl3::hint::set("SDL_VIDEO_DRIVER", "dummy");
+    let sdl = sdl3::init().unwrap();
+    let window = sdl.video().unwrap().window("test", 200, 100).build().unwrap();
+    let mut writing_canvas = WritingCanvas::new(Rc::new(RefCell::new(window.into_canvas())))
+      .with_erase_whole_strokes(erase_whole_strokes);
+    writing_canvas.set_bound(Rect { x1: 0.0, y1: 0.0, x2: 200.0, y2: 100.0 });
+    test(&mut writing_canvas);
+  }
+  
+  // Pen down at the first point, through the others and up at the last
+  fn draw(writing_canvas: &mut WritingCanvas, points: &[(f32, f32)], eraser: bool) {
+    let pointer = PointerId::pen(0);
+    let (x, y) = points[0];
+    writing_canvas.pointer_event(&PointerEvent::Down { pointer, x, y, timestamp: 0, eraser, pressure: None });
+    for (i, &(x, y)) in points.iter().enumerate().skip(1) {
+      writing_canvas.pointer_event(&PointerEvent::Motion { pointer, x, y, timestamp: i as u64 });
+    }
+    let (x, y) = points[points.len() - 1];
+    writing_canvas.pointer_event(&PointerEvent::Up { pointer, x, y, timestamp: points.len() as u64 });
+  }
+  
+  #[test]
+  fn clearing_is_a_new_update() {
+    with_canvas(false, |writing_canvas| {
+      draw(writing_canvas, &[(10.0, 50.0), (190.0, 50.0)], false);
+      let drawn = writing_canvas.get_update_count();
+      writing_canvas.clear();
+      let cleared = writing_canvas.get_update_count();
+      assert_ne!(cleared, drawn);
+      
+      // Also when there was nothing to clear
+      writing_canvas.reset();
+      assert_ne!(writing_canvas.get_update_count(), cleared);
+    });
+  }
+  
+  #[test]
+  fn scratch_out_removes_every_part_of_the_scribble() {
+    with_canvas(false, |writing_canvas| {
+      draw(writing_canvas, &[(100.0, 42.0), (100.0, 47.0), (100.0, 58.0)], false);
+      // Leaves the canvas at the top and comes back before scribbling
+      draw(writing_canvas, &[(60.0, 40.0), (60.0, -20.0), (60.0, 40.0), (140.0, 60.0), (60.0, 40.0), (140.0, 60.0), (60.0, 40.0), (140.0, 60.0)], false);
+      assert!(writing_canvas.is_empty());
+      
+      assert!(writing_canvas.undo());
+      assert_eq!(writing_canvas.all_strokes.len(), 1);
+    });
+  }
+  
+  #[test]
+  fn eraser_cuts_stroke_between_its_points() {
+    with_canvas(false, |writing_canvas| {
+      draw(writing_canvas, &[(10.0, 50.0), (190.0, 50.0)], false);
+      draw(writing_canvas, &[(100.0, 20.0), (100.0, 80.0)], true);
+      
+      let strokes = &writing_canvas.all_strokes;
+      assert_eq!(strokes.len(), 2);
+      let left_end = &strokes[0].points.last().unwrap().point;
+      let right_start = &strokes[1].points[0].point;
+      assert!((left_end.x - 92.0).abs() < 1e-3, "left part ends at {left_end}");
+      assert!((right_start.x - 108.0).abs() < 1e-3, "right part starts at {right_start}");
+      assert_eq!(strokes[0].points[0].point.x, 10.0);
+      assert_eq!(strokes[1].points.last().unwrap().point.x, 190.0);
+    });
+  }
+  
+  #[test]
+  fn eraser_removes_stroke_it_crosses_between_points() {
+    with_canvas(true, |writing_canvas| {
+      draw(writing_canvas, &[(10.0, 50.0), (190.0, 50.0)], false);
+      draw(writing_canvas, &[(100.0, 20.0), (100.0, 40.0)], true);
+      assert_eq!(writing_canvas.all_strokes.len(), 1);
+      draw(writing_canvas, &[(100.0, 20.0), (100.0, 80.0)], true);
+      assert!(writing_canvas.is_empty());
+    });
+  }
+}
@@ -0,0 +1,81 @@
+// Prints the GLYPHS table of src/font.rs, rasterized with FreeType's
+// hinting from DejaVu Sans Mono. Build and run with:
+//
+//   cc tools/fontgen.c $(pkg-config --cflags --libs freetype2) -o fontgen
+//   ./fontgen /usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf
+//
+// and put the output in place of the table.
+
+#include <ft2build.h>
+#include FT_FREETYPE_H
+#include <stdio.h>
+
+// Size of a glyph in src/font.rs
+#define GLYPH_WIDTH 8
+#define GLYPH_HEIGHT 16
+
+// Largest size at which every glyph fits into the width
+#define PIXEL_SIZE 13
+// Row the glyphs stand on, leaves room for descenders below
+#define BASELINE 12
+
+int main(int argc, char **argv) {
+  if (argc != 2) {
+    fprintf(stderr, "usage: %s DejaVuSansMono.ttf\n", argv[0]);
+    return 1;
+  }
+
+  FT_Library library;
+  FT_Face face;
+  if (FT_Init_FreeType(&library) || FT_New_Face(library, argv[1], 0, &face) || FT_Set_Pixel_Sizes(face, 0, PIXEL_SIZE)) {
+    fprintf(stderr, "%s could not be loaded\n", argv[1]);
+    return 1;
+  }
+
+  for (int c = ' '; c <= '~'; c++) {
+    // Monochrome rendering uses the hinting of the font, which
+    // keeps the strokes whole at this size
+    if (FT_Load_Char(face, c, FT_LOAD_RENDER | FT_LOAD_TARGET_MONO)) {
+      fprintf(stderr, "'%c' could not be rendered\n", c);
+      return 1;
+    }
+
+    // Centered on the advance, glyphs reaching past the right
+    // edge are moved left
+    FT_GlyphSlot glyph = face->glyph;
+    int width = glyph->bitmap.width;
+    int left = (GLYPH_WIDTH - (int)(glyph->advance.x >> 6)) / 2 + glyph->bitmap_left;
+    if (left + width > GLYPH_WIDTH) {
+      left = GLYPH_WIDTH - width;
+    }
+    if (left < 0) {
+      left = 0;
+    }
+    int top = BASELINE - glyph->bitmap_top;
+
+    unsigned char rows[GLYPH_HEIGHT] = {0};
+    for (int y = 0; y < (int)glyph->bitmap.rows; y++) {
+      for (int x = 0; x < width; x++) {
+        if (!(glyph->bitmap.buffer[y * glyph->bitmap.pitch + x / 8] & (0x80 >> (x % 8)))) {
+          continue;
+        }
+
+        if (left + x >= GLYPH_WIDTH || top + y < 0 || top + y >= GLYPH_HEIGHT) {
+          fprintf(stderr, "'%c' does not fit\n", c);
+          return 1;
+        }
+        rows[top + y] |= 0x80 >> (left + x);
+      }
+    }
+
+    printf("  // '%s%c'\n  [", c == '\'' || c == '\\' ? "\\" : "", c);
+    for (int y = 0; y < GLYPH_HEIGHT; y++) {
+      printf("0x%02X%s", rows[y], y + 1 < GLYPH_HEIGHT ? ", " : "");
+    }
+    printf("]%s\n", c < '~' ? "," : "");
+  }
+
+  FT_Done_Face(face);
+  FT_Done_FreeType(library);
+  return 0;
+}